@@ -0,0 +1,21 @@
+//! Embeds the commit the bot is built from as `GIT_HASH`, shown by `/status`.
+//! A `GIT_HASH` set in the environment wins, for builds without `.git`.
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    if std::env::var("GIT_HASH").is_ok_and(|hash| !hash.is_empty()) {
+        return;
+    }
+
+    let output = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output();
+    if let Some(output) = output.ok().filter(|output| output.status.success()) {
+        let hash = String::from_utf8_lossy(&output.stdout);
+        if !hash.trim().is_empty() {
+            println!("cargo:rustc-env=GIT_HASH={}", hash.trim());
+        }
+    }
+}
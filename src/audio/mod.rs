@@ -1,34 +1,35 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::fs;
-use crate::api::hiroyuki;
 use crate::db::language::Language;
+use crate::tts::{self, voices};
+use tracing::info;
 
 const CONNECT_EN: &str = "Connected to VC";
 const CONNECT_JP: &str = "接続しました";
 
 pub async fn ensure_audio_assets() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let audio_dir = Path::new("assets/audio");
+    let audio_dir = crate::config::get().audio.assets_dir.as_path();
     if !audio_dir.exists() {
-        println!("📁 Creating audio directory");
+        info!("Creating audio directory");
         fs::create_dir_all(audio_dir)?;
     }
 
     // Check and generate English audio
     let en_path = audio_dir.join("connect_en.mp3");
     if !en_path.exists() {
-        println!("🎵 Generating English connect audio");
-        let audio_data = hiroyuki::get_hiroyuki_voice(CONNECT_EN).await?;
-        fs::write(&en_path, audio_data)?;
-        println!("✅ Saved English connect audio");
+        info!("Generating English connect audio");
+        let audio_data = tts::synthesize(&voices::find(None), CONNECT_EN).await?;
+        fs::write(&en_path, audio_data.as_slice())?;
+        info!("Saved English connect audio");
     }
 
     // Check and generate Japanese audio
     let jp_path = audio_dir.join("connect_jp.mp3");
     if !jp_path.exists() {
-        println!("🎵 Generating Japanese connect audio");
-        let audio_data = hiroyuki::get_hiroyuki_voice(CONNECT_JP).await?;
-        fs::write(&jp_path, audio_data)?;
-        println!("✅ Saved Japanese connect audio");
+        info!("Generating Japanese connect audio");
+        let audio_data = tts::synthesize(&voices::find(None), CONNECT_JP).await?;
+        fs::write(&jp_path, audio_data.as_slice())?;
+        info!("Saved Japanese connect audio");
     }
 
     Ok(())
@@ -39,5 +40,5 @@ pub fn get_connect_audio_path(language: Language) -> PathBuf {
         Language::English => "connect_en.mp3",
         Language::Japanese => "connect_jp.mp3",
     };
-    Path::new("assets/audio").join(filename)
+    crate::config::get().audio.assets_dir.join(filename)
 }
@@ -0,0 +1,89 @@
+//! Command line flags.
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: discord-bot [OPTIONS] [COMMAND]
+
+Commands:
+  healthcheck       Exit successfully if the running bot reports ready on /readyz
+
+Options:
+  --config <PATH>   Read the configuration from PATH instead of config.toml
+  --check-config    Validate the configuration and exit
+  --purge-commands  Delete the registered slash commands (in commands.dev_guilds
+                    if set, otherwise the global ones) and exit
+  -h, --help        Print this help and exit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Healthcheck,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    pub command: Option<Command>,
+    pub config: Option<PathBuf>,
+    pub check_config: bool,
+    pub purge_commands: bool,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the arguments after the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    let path = args.next().ok_or("--config needs a path")?;
+                    parsed.config = Some(PathBuf::from(path));
+                }
+                "--check-config" => parsed.check_config = true,
+                "--purge-commands" => parsed.purge_commands = true,
+                "-h" | "--help" => parsed.help = true,
+                "healthcheck" if parsed.command.is_none() => parsed.command = Some(Command::Healthcheck),
+                _ => match arg.strip_prefix("--config=") {
+                    Some(path) => parsed.config = Some(PathBuf::from(path)),
+                    None => return Err(format!("unknown argument {:?}", arg)),
+                },
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn config_path_in_either_form() {
+        assert_eq!(parse(&["--config", "a.toml"]).unwrap().config, Some(PathBuf::from("a.toml")));
+        assert_eq!(parse(&["--config=b.toml"]).unwrap().config, Some(PathBuf::from("b.toml")));
+        assert!(parse(&["--config"]).is_err());
+    }
+
+    #[test]
+    fn check_config_and_unknown_flags() {
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+        assert!(parse(&["--check-config"]).unwrap().check_config);
+        assert!(parse(&["--purge-commands"]).unwrap().purge_commands);
+        assert!(parse(&["--chek-config"]).is_err());
+    }
+
+    #[test]
+    fn healthcheck_subcommand_takes_options() {
+        let args = parse(&["healthcheck", "--config", "a.toml"]).unwrap();
+        assert_eq!(args.command, Some(Command::Healthcheck));
+        assert_eq!(args.config, Some(PathBuf::from("a.toml")));
+        assert!(parse(&["healthcheck", "healthcheck"]).is_err());
+    }
+}
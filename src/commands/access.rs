@@ -0,0 +1,108 @@
+//! Who may run a command, on top of the Discord permissions in
+//! [`SlashCommand::required_permissions`](super::SlashCommand::required_permissions).
+//!
+//! Commands that change the call or the server's settings are for managers:
+//! members with Manage Server or the guild's TTS manager role, set with
+//! `/tts manager`. Operational commands are for the bot's owners: the
+//! application's owner or team members, plus `commands.owners`.
+//!
+//! The registry checks a command's access before running it and before
+//! handling its components and modals. Commands that are only partly for
+//! managers, like `/tts`, call [`require_manager`] for those subcommands.
+
+use serenity::http::Http;
+use serenity::model::guild::Member;
+use serenity::model::id::{GuildId, RoleId, UserId};
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+use super::BotError;
+
+/// The application's owner or team members, loaded once on startup.
+static APPLICATION_OWNERS: OnceLock<HashSet<UserId>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Everyone,
+    /// Members with Manage Server or the guild's TTS manager role.
+    Manager,
+    /// The bot's owners.
+    Owner,
+}
+
+/// Looks up who owns the application, for [`Access::Owner`].
+pub async fn load_owners(http: &Http) {
+    if APPLICATION_OWNERS.get().is_some() {
+        return;
+    }
+    match http.get_current_application_info().await {
+        Ok(info) => {
+            let owners = match (info.team, info.owner) {
+                (Some(team), _) => team.members.into_iter().map(|member| member.user.id).collect(),
+                (None, Some(owner)) => HashSet::from([owner.id]),
+                (None, None) => HashSet::new(),
+            };
+            info!("Loaded {} application owner(s)", owners.len());
+            let _ = APPLICATION_OWNERS.set(owners);
+        }
+        Err(e) => warn!("Failed to look up the application's owners: {}", e),
+    }
+}
+
+pub fn is_owner(user: UserId) -> bool {
+    crate::config::get().commands.owners.contains(&user.get())
+        || APPLICATION_OWNERS.get().is_some_and(|owners| owners.contains(&user))
+}
+
+/// Whether a member with `permissions` and `roles` manages TTS in a guild
+/// whose manager role is `manager_role`.
+pub fn is_manager(permissions: Option<Permissions>, roles: &[RoleId], manager_role: Option<RoleId>) -> bool {
+    permissions.is_some_and(|permissions| permissions.manage_guild())
+        || manager_role.is_some_and(|role| roles.contains(&role))
+}
+
+/// Fails unless `member` is a manager in `guild_id`.
+pub async fn require_manager(ctx: &Context, guild_id: Option<GuildId>, member: Option<&Member>) -> Result<(), BotError> {
+    let (Some(guild_id), Some(member)) = (guild_id, member) else {
+        return Err(BotError::GuildOnly);
+    };
+    let manager_role = crate::db::store(ctx).await.get_manager_role(guild_id.get()).await?.map(RoleId::new);
+    if is_manager(member.permissions, &member.roles, manager_role) {
+        Ok(())
+    } else {
+        Err(BotError::NotManager(manager_role))
+    }
+}
+
+/// Fails unless `user` (`member` in guilds) has `access`.
+pub async fn check(
+    ctx: &Context,
+    access: Access,
+    guild_id: Option<GuildId>,
+    user: UserId,
+    member: Option<&Member>,
+) -> Result<(), BotError> {
+    match access {
+        Access::Everyone => Ok(()),
+        Access::Manager => require_manager(ctx, guild_id, member).await,
+        Access::Owner if is_owner(user) => Ok(()),
+        Access::Owner => Err(BotError::OwnerOnly),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn managers_have_manage_server_or_the_role() {
+        let role = RoleId::new(5);
+        assert!(is_manager(Some(Permissions::MANAGE_GUILD), &[], None));
+        assert!(is_manager(Some(Permissions::empty()), &[RoleId::new(4), role], Some(role)));
+        assert!(!is_manager(Some(Permissions::SEND_MESSAGES), &[RoleId::new(4)], Some(role)));
+        assert!(!is_manager(None, &[role], None));
+    }
+}
@@ -1,7 +1,11 @@
 use serenity::builder::{CreateCommand, CreateCommandOption};
-use serenity::model::application::{CommandOptionType, ResolvedOption, ResolvedValue};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue};
+use serenity::async_trait;
+use serenity::prelude::*;
 
-pub fn run(options: &[ResolvedOption]) -> String {
+use crate::commands::{BotError, Reply, SlashCommand};
+
+fn run(options: &[ResolvedOption]) -> String {
     if let Some(ResolvedOption {
         value: ResolvedValue::Attachment(attachment), ..
     }) = options.first()
@@ -12,11 +16,25 @@ pub fn run(options: &[ResolvedOption]) -> String {
     }
 }
 
-pub fn register() -> CreateCommand {
-    CreateCommand::new("attachmentinput")
-        .description("Test command for attachment input")
-        .add_option(
-            CreateCommandOption::new(CommandOptionType::Attachment, "attachment", "A file")
-                .required(true),
-        )
-}
\ No newline at end of file
+pub struct AttachmentInput;
+
+#[async_trait]
+impl SlashCommand for AttachmentInput {
+    fn name(&self) -> &'static str {
+        "attachmentinput"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Test command for attachment input")
+            .description_localized("ja", "添付ファイル入力のテスト")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Attachment, "attachment", "A file")
+                    .required(true),
+            )
+    }
+
+    async fn execute(&self, _ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        Ok(Reply::from(run(&command.data.options())))
+    }
+}
@@ -0,0 +1,130 @@
+//! Ranking of autocomplete suggestions.
+//!
+//! Exact matches come first, then values starting with what was typed, then
+//! values with a word starting with it, values containing it and finally
+//! values containing its characters in order. Ties keep the order the
+//! candidates were given in.
+
+use serenity::builder::AutocompleteChoice;
+
+/// Discord shows at most this many suggestions.
+pub const MAX_CHOICES: usize = 25;
+
+/// Choice names longer than this are rejected by Discord.
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Something that can be suggested: the `label` shown to the user and the
+/// `value` the option is set to. Both are matched against the query.
+pub struct Candidate {
+    pub label: String,
+    pub value: String,
+}
+
+impl Candidate {
+    pub fn new(label: impl Into<String>, value: impl Into<String>) -> Self {
+        Self { label: label.into(), value: value.into() }
+    }
+}
+
+/// How well `text` matches `query`, lower is better. `None` if it doesn't.
+fn score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase();
+    if query.is_empty() || text == query {
+        return Some(0);
+    }
+    if text.starts_with(query) {
+        return Some(1);
+    }
+    if text.split(|c: char| c.is_whitespace() || c == '_' || c == '-').any(|word| word.starts_with(query)) {
+        return Some(2);
+    }
+    if text.contains(query) {
+        return Some(3);
+    }
+
+    // Every character of the query in order, the fewer skipped the better
+    let mut skipped = 0;
+    let mut chars = text.chars();
+    for wanted in query.chars() {
+        loop {
+            match chars.next() {
+                Some(c) if c == wanted => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+    }
+    Some(4 + skipped)
+}
+
+/// The candidates matching `query`, best first.
+pub fn rank(query: &str, candidates: impl IntoIterator<Item = Candidate>) -> Vec<Candidate> {
+    let query = query.trim().to_lowercase();
+    let mut scored = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let best = match (score(&query, &candidate.label), score(&query, &candidate.value)) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            best.map(|score| (score, candidate))
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by_key(|(score, _)| *score);
+    scored.into_iter().take(MAX_CHOICES).map(|(_, candidate)| candidate).collect()
+}
+
+/// The ranked candidates as string choices.
+pub fn choices(query: &str, candidates: impl IntoIterator<Item = Candidate>) -> Vec<AutocompleteChoice> {
+    rank(query, candidates)
+        .into_iter()
+        .map(|candidate| {
+            let label = if candidate.label.chars().count() > MAX_NAME_LENGTH {
+                let mut label = candidate.label.chars().take(MAX_NAME_LENGTH - 1).collect::<String>();
+                label.push('…');
+                label
+            } else {
+                candidate.label
+            };
+            AutocompleteChoice::new(label, candidate.value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(query: &str, values: &[&str]) -> Vec<String> {
+        rank(query, values.iter().map(|value| Candidate::new(*value, *value)))
+            .into_iter()
+            .map(|candidate| candidate.value)
+            .collect()
+    }
+
+    #[test]
+    fn prefixes_beat_substrings_beat_fuzzy_matches() {
+        let values = ["serenity", "rust-analyzer", "Rustacean", "trust", "rust", "ruby"];
+        assert_eq!(ranked("rust", &values), ["rust", "rust-analyzer", "Rustacean", "trust"]);
+        assert_eq!(ranked("ana", &values), ["rust-analyzer"]);
+        // Fewer skipped characters rank higher
+        assert_eq!(ranked("rsa", &values), ["Rustacean", "rust-analyzer"]);
+    }
+
+    #[test]
+    fn empty_query_keeps_order_up_to_the_limit() {
+        let values = (0..30).map(|i| format!("word{}", i)).collect::<Vec<_>>();
+        let values = values.iter().map(String::as_str).collect::<Vec<_>>();
+        let ranked = ranked("", &values);
+        assert_eq!(ranked.len(), MAX_CHOICES);
+        assert_eq!(ranked[0], "word0");
+    }
+
+    #[test]
+    fn labels_match_too() {
+        let candidates = vec![Candidate::new("#general", "1"), Candidate::new("#random", "2")];
+        let ranked = rank("gen", candidates);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].value, "1");
+    }
+}
@@ -0,0 +1,87 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::prelude::*;
+use serenity::async_trait;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+
+pub const MAX_READING_LENGTH: usize = 32;
+
+/// Rejects readings too long to be read before every message.
+pub fn check_reading(reading: &str) -> Result<(), BotError> {
+    if reading.chars().count() > MAX_READING_LENGTH {
+        return Err(BotError::invalid(
+            format!("Please keep your reading under {} characters", MAX_READING_LENGTH),
+            format!("読みは {} 文字以内にしてください", MAX_READING_LENGTH),
+        ));
+    }
+    Ok(())
+}
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let store = crate::db::store(ctx).await;
+    let user_id = command.user.id;
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+
+    let reading = command
+        .data
+        .options
+        .first()
+        .and_then(|option| option.value.as_str())
+        .map(str::trim)
+        .filter(|reading| !reading.is_empty());
+
+    match reading {
+        Some(reading) => {
+            check_reading(reading)?;
+            store.set_spoken_name(guild_id.get(), user_id.get(), reading).await?;
+            Ok(match language {
+                Language::English => format!("I'll call you \"{}\" from now on", reading),
+                Language::Japanese => format!("これから「{}」と読み上げます", reading),
+            })
+        }
+        None => {
+            store.clear_spoken_name(guild_id.get(), user_id.get()).await?;
+            Ok(match language {
+                Language::English => "I'll read your display name again".to_string(),
+                Language::Japanese => "表示名で読み上げるように戻しました".to_string(),
+            })
+        }
+    }
+}
+
+pub struct CallMe;
+
+#[async_trait]
+impl SlashCommand for CallMe {
+    fn name(&self) -> &'static str {
+        "callme"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Set how your name is read aloud (leave empty to reset)")
+            .description_localized("ja", "自分の名前の読み方を設定します（空でリセット）")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "reading", "How your name should be pronounced")
+                    .max_length(MAX_READING_LENGTH as u16),
+            )
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &[
+            "/callme reading:たろう",
+            "/callme",
+        ]
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+
+    /// Only the user's own name is affected, no need to tell the channel
+    fn ephemeral(&self) -> bool {
+        true
+    }
+}
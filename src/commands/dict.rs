@@ -0,0 +1,123 @@
+use serenity::builder::{AutocompleteChoice, CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue};
+use serenity::prelude::*;
+use serenity::async_trait;
+use crate::commands::autocomplete::{self, Candidate};
+use crate::commands::{BotError, Reply, SlashCommand};
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let store = crate::db::store(ctx).await;
+
+    let options = command.data.options();
+    let Some(ResolvedOption { name: subcommand, value: ResolvedValue::SubCommand(args), .. }) = options.first() else {
+        return Err(BotError::invalid("Please choose add, remove or list", "add・remove・list のいずれかを選んでください"));
+    };
+
+    let string_arg = |name: &str| {
+        args.iter().find_map(|option| match option.value {
+            ResolvedValue::String(value) if option.name == name => Some(value.trim()),
+            _ => None,
+        })
+    };
+
+    match *subcommand {
+        "add" => {
+            let word = string_arg("word").filter(|w| !w.is_empty()).ok_or_else(missing_word)?;
+            let reading = string_arg("reading")
+                .filter(|r| !r.is_empty())
+                .ok_or_else(|| BotError::invalid("Please provide a reading", "読みを入力してください"))?;
+            store.add_dictionary_entry(guild_id.get(), word, reading).await?;
+            Ok(format!("Registered: {} → {}", word, reading))
+        }
+        "remove" => {
+            let word = string_arg("word").ok_or_else(missing_word)?;
+            if store.remove_dictionary_entry(guild_id.get(), word).await? {
+                Ok(format!("Removed: {}", word))
+            } else {
+                Err(BotError::invalid(
+                    format!("\"{}\" is not in the dictionary", word),
+                    format!("「{}」は辞書に登録されていません", word),
+                ))
+            }
+        }
+        "list" => {
+            let entries = store.get_dictionary(guild_id.get()).await?;
+            if entries.is_empty() {
+                return Ok("The dictionary is empty".to_string());
+            }
+            Ok(entries
+                .iter()
+                .map(|(word, reading)| format!("{} → {}", word, reading))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        _ => Err(BotError::Internal(format!("unknown subcommand /dict {}", subcommand))),
+    }
+}
+
+fn missing_word() -> BotError {
+    BotError::invalid("Please provide a word", "単語を入力してください")
+}
+
+pub struct Dict;
+
+#[async_trait]
+impl SlashCommand for Dict {
+    fn name(&self) -> &'static str {
+        "dict"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Manage the server's pronunciation dictionary")
+            .description_localized("ja", "サーバーの読み上げ辞書を編集します")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Add or update a reading")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "word", "The word as written")
+                            .required(true),
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "reading", "How the word should be read")
+                            .required(true),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Remove a reading")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "word", "The word to remove")
+                            .required(true)
+                            .set_autocomplete(true),
+                    ),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "list", "List all readings"))
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &[
+            "/dict add word:Rust reading:ラスト",
+            "/dict remove word:Rust",
+            "/dict list",
+        ]
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+
+    /// Suggests the guild's dictionary words for `/dict remove`
+    async fn autocomplete(&self, ctx: &Context, command: &CommandInteraction) -> Result<Vec<AutocompleteChoice>, BotError> {
+        let (Some(guild_id), Some(focused)) = (command.guild_id, command.data.autocomplete()) else {
+            return Ok(Vec::new());
+        };
+        if focused.name != "word" {
+            return Ok(Vec::new());
+        }
+        let entries = crate::db::store(ctx).await.get_dictionary(guild_id.get()).await?;
+        let candidates = entries
+            .into_iter()
+            .map(|(word, reading)| Candidate::new(format!("{} → {}", word, reading), word));
+        Ok(autocomplete::choices(focused.value, candidates))
+    }
+}
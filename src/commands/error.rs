@@ -0,0 +1,238 @@
+//! Errors a command can fail with.
+//!
+//! Every [`BotError`] is either the user's mistake, a transient failure
+//! worth retrying or a bug. The dispatcher logs it at the matching level and
+//! answers with an ephemeral message in the guild's language.
+
+use serenity::model::id::RoleId;
+use serenity::model::permissions::Permissions;
+use std::fmt;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+use crate::db::language::Language;
+
+#[derive(Debug)]
+pub enum BotError {
+    /// Used outside of a server.
+    GuildOnly,
+    NotInVoiceChannel,
+    MissingPermission(Permissions),
+    /// Needs Manage Server or the guild's TTS manager role, if it has one.
+    NotManager(Option<RoleId>),
+    OwnerOnly,
+    /// The user has to wait this long before running the command again.
+    Cooldown(Duration),
+    /// Someone in the guild just ran the command, so wait this long.
+    GuildCooldown(Duration),
+    /// Input the command can't use, phrased for the user in both languages.
+    InvalidInput { english: String, japanese: String },
+
+    Database(rusqlite::Error),
+    Discord(Box<serenity::Error>),
+    /// Joining, leaving or deafening in a voice channel failed.
+    Voice(String),
+    /// The TTS backend failed or is unavailable.
+    Backend(String),
+
+    /// Something that shouldn't happen, like a missing client resource or a
+    /// command that panicked.
+    Internal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    User,
+    Transient,
+    Internal,
+}
+
+impl BotError {
+    pub fn invalid(english: impl Into<String>, japanese: impl Into<String>) -> Self {
+        BotError::InvalidInput { english: english.into(), japanese: japanese.into() }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BotError::GuildOnly
+            | BotError::NotInVoiceChannel
+            | BotError::MissingPermission(_)
+            | BotError::NotManager(_)
+            | BotError::OwnerOnly
+            | BotError::Cooldown(_)
+            | BotError::GuildCooldown(_)
+            | BotError::InvalidInput { .. } => ErrorKind::User,
+            BotError::Database(_) | BotError::Discord(_) | BotError::Voice(_) | BotError::Backend(_) => {
+                ErrorKind::Transient
+            }
+            BotError::Internal(_) => ErrorKind::Internal,
+        }
+    }
+
+    /// Logs the error at a level that matches its kind: user mistakes are
+    /// routine, transient failures are worth a look, bugs need fixing.
+    pub fn log(&self) {
+        match self.kind() {
+            ErrorKind::User => debug!("Command refused: {}", self),
+            ErrorKind::Transient => warn!("Command failed: {}", self),
+            ErrorKind::Internal => error!("Command failed: {}", self),
+        }
+    }
+
+    /// What to tell the user.
+    pub fn message(&self, language: Language) -> String {
+        match (self, language) {
+            (BotError::GuildOnly, Language::English) => "This command can only be used in servers".to_string(),
+            (BotError::GuildOnly, Language::Japanese) => "このコマンドはサーバー内でのみ使えます".to_string(),
+            (BotError::NotInVoiceChannel, Language::English) => "You must be in a voice channel".to_string(),
+            (BotError::NotInVoiceChannel, Language::Japanese) => "ボイスチャンネルに参加してから使ってください".to_string(),
+            (BotError::MissingPermission(permissions), Language::English) => format!(
+                "You need the {} permission to use this command",
+                permission_names(*permissions, language)
+            ),
+            (BotError::MissingPermission(permissions), Language::Japanese) => format!(
+                "このコマンドを使うには「{}」権限が必要です",
+                permission_names(*permissions, language)
+            ),
+            (BotError::NotManager(None), Language::English) => {
+                "You need the Manage Server permission to use this command".to_string()
+            }
+            (BotError::NotManager(None), Language::Japanese) => {
+                "このコマンドを使うには「サーバー管理」権限が必要です".to_string()
+            }
+            (BotError::NotManager(Some(role)), Language::English) => {
+                format!("You need the Manage Server permission or the <@&{}> role to use this command", role)
+            }
+            (BotError::NotManager(Some(role)), Language::Japanese) => {
+                format!("このコマンドを使うには「サーバー管理」権限か <@&{}> ロールが必要です", role)
+            }
+            (BotError::OwnerOnly, Language::English) => "Only the bot's owners can use this command".to_string(),
+            (BotError::OwnerOnly, Language::Japanese) => "このコマンドは Bot の管理者のみ使えます".to_string(),
+            (BotError::Cooldown(remaining), Language::English) => {
+                format!("Please wait {}s before using this command again", remaining.as_secs() + 1)
+            }
+            (BotError::Cooldown(remaining), Language::Japanese) => {
+                format!("あと {} 秒待ってからもう一度使ってください", remaining.as_secs() + 1)
+            }
+            (BotError::GuildCooldown(remaining), Language::English) => format!(
+                "This command was just used in this server, please wait {}s",
+                remaining.as_secs() + 1
+            ),
+            (BotError::GuildCooldown(remaining), Language::Japanese) => {
+                format!("このサーバーで使われたばかりです。あと {} 秒待ってください", remaining.as_secs() + 1)
+            }
+            (BotError::InvalidInput { english, .. }, Language::English) => english.clone(),
+            (BotError::InvalidInput { japanese, .. }, Language::Japanese) => japanese.clone(),
+            (BotError::Database(_), Language::English) => {
+                "Couldn't reach the database, please try again in a moment".to_string()
+            }
+            (BotError::Database(_), Language::Japanese) => {
+                "データベースにアクセスできませんでした。少し待ってからもう一度お試しください".to_string()
+            }
+            (BotError::Discord(_), Language::English) => "Discord didn't respond, please try again".to_string(),
+            (BotError::Discord(_), Language::Japanese) => "Discord との通信に失敗しました。もう一度お試しください".to_string(),
+            (BotError::Voice(_), Language::English) => {
+                "Couldn't connect to the voice channel, please try again".to_string()
+            }
+            (BotError::Voice(_), Language::Japanese) => {
+                "ボイスチャンネルに接続できませんでした。もう一度お試しください".to_string()
+            }
+            (BotError::Backend(_), Language::English) => {
+                "The speech service is unavailable right now, please try again later".to_string()
+            }
+            (BotError::Backend(_), Language::Japanese) => {
+                "音声合成サービスが現在利用できません。しばらくしてからお試しください".to_string()
+            }
+            (BotError::Internal(_), Language::English) => {
+                "Something went wrong on our side. It has been logged.".to_string()
+            }
+            (BotError::Internal(_), Language::Japanese) => "内部エラーが発生しました。ログに記録されています".to_string(),
+        }
+    }
+}
+
+/// The names Discord's client shows for `permissions`, falling back to
+/// serenity's names for the ones commands don't usually ask for.
+pub fn permission_names(permissions: Permissions, language: Language) -> String {
+    const NAMES: &[(Permissions, &str, &str)] = &[
+        (Permissions::ADMINISTRATOR, "Administrator", "管理者"),
+        (Permissions::MANAGE_GUILD, "Manage Server", "サーバー管理"),
+        (Permissions::MANAGE_CHANNELS, "Manage Channels", "チャンネルの管理"),
+        (Permissions::MANAGE_ROLES, "Manage Roles", "ロールの管理"),
+        (Permissions::MANAGE_MESSAGES, "Manage Messages", "メッセージの管理"),
+        (Permissions::MOVE_MEMBERS, "Move Members", "メンバーを移動"),
+    ];
+
+    let mut names = Vec::new();
+    let mut rest = permissions;
+    for (permission, english, japanese) in NAMES {
+        if permissions.contains(*permission) {
+            rest.remove(*permission);
+            names.push(match language {
+                Language::English => english.to_string(),
+                Language::Japanese => japanese.to_string(),
+            });
+        }
+    }
+    names.extend(rest.get_permission_names().into_iter().map(str::to_string));
+    names.join(", ")
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::GuildOnly => write!(f, "used outside of a server"),
+            BotError::NotInVoiceChannel => write!(f, "user is not in a voice channel"),
+            BotError::MissingPermission(permissions) => write!(f, "missing permission {}", permissions),
+            BotError::NotManager(_) => write!(f, "not a TTS manager"),
+            BotError::OwnerOnly => write!(f, "not an owner"),
+            BotError::Cooldown(remaining) => write!(f, "on cooldown for {:?}", remaining),
+            BotError::GuildCooldown(remaining) => write!(f, "on guild cooldown for {:?}", remaining),
+            BotError::InvalidInput { english, .. } => write!(f, "invalid input: {}", english),
+            BotError::Database(e) => write!(f, "database error: {}", e),
+            BotError::Discord(e) => write!(f, "Discord error: {}", e),
+            BotError::Voice(e) => write!(f, "voice error: {}", e),
+            BotError::Backend(e) => write!(f, "TTS backend error: {}", e),
+            BotError::Internal(e) => write!(f, "internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<rusqlite::Error> for BotError {
+    fn from(e: rusqlite::Error) -> Self {
+        BotError::Database(e)
+    }
+}
+
+impl From<serenity::Error> for BotError {
+    fn from(e: serenity::Error) -> Self {
+        BotError::Discord(Box::new(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_and_localized_messages() {
+        let error = BotError::MissingPermission(Permissions::MANAGE_GUILD);
+        assert_eq!(error.kind(), ErrorKind::User);
+        assert!(error.message(Language::English).contains("Manage Server"));
+        assert!(error.message(Language::Japanese).contains("サーバー管理"));
+
+        let error = BotError::invalid("Please provide a word", "単語を入力してください");
+        assert_eq!(error.message(Language::Japanese), "単語を入力してください");
+
+        assert_eq!(BotError::Database(rusqlite::Error::InvalidQuery).kind(), ErrorKind::Transient);
+        assert_eq!(BotError::Internal("panicked".into()).kind(), ErrorKind::Internal);
+        assert_eq!(
+            BotError::Cooldown(Duration::from_millis(2500)).message(Language::English),
+            "Please wait 3s before using this command again"
+        );
+        assert!(BotError::NotManager(Some(RoleId::new(5))).message(Language::English).contains("<@&5>"));
+        assert_eq!(BotError::OwnerOnly.kind(), ErrorKind::User);
+    }
+}
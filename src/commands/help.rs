@@ -0,0 +1,590 @@
+//! `/help`: every command in the [`REGISTRY`](super::REGISTRY), generated from
+//! the builders that are registered with Discord, so it can't drift from what
+//! the client shows.
+//!
+//! The list is paginated with buttons, and picking a command in the select
+//! menu opens its page with usage, options, examples and who may run it.
+//! Descriptions use the builders' Japanese localizations in Japanese guilds.
+
+use serde_json::Value;
+use serenity::async_trait;
+use serenity::builder::{
+    AutocompleteChoice, CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+    CreateSelectMenuKind, CreateSelectMenuOption,
+};
+use serenity::model::application::{
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, ComponentInteractionDataKind,
+    ResolvedValue,
+};
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+use std::time::Duration;
+
+use crate::commands::autocomplete::{self, Candidate};
+use crate::commands::error::permission_names;
+use crate::commands::registry::guild_language;
+use crate::commands::{Access, BotError, Reply, SlashCommand, REGISTRY};
+use crate::db::language::Language;
+
+/// Commands listed on each page, at most 25 for the select menu.
+const PER_PAGE: usize = 8;
+
+/// Discord's limits on embed descriptions, field values and select menu
+/// option texts.
+const MAX_DESCRIPTION: usize = 4096;
+const MAX_FIELD: usize = 1024;
+const MAX_OPTION_TEXT: usize = 100;
+
+// Option and command types in Discord's command JSON
+const SUB_COMMAND: u64 = 1;
+const SUB_COMMAND_GROUP: u64 = 2;
+const USER_COMMAND: u64 = 2;
+const MESSAGE_COMMAND: u64 = 3;
+
+/// One way to invoke a command.
+#[derive(Debug, PartialEq)]
+struct Usage {
+    /// E.g. `/dict add word:<word> reading:<reading>`.
+    line: String,
+    description: String,
+}
+
+/// An option of a command or of one of its subcommands.
+#[derive(Debug, PartialEq)]
+struct Argument {
+    name: String,
+    description: String,
+    required: bool,
+    choices: Vec<String>,
+}
+
+/// A command as /help shows it.
+struct Entry {
+    /// The name the command is dispatched under.
+    id: &'static str,
+    /// `/name` for slash commands, the menu entry for context menu commands.
+    title: String,
+    context_menu: bool,
+    description: String,
+    usages: Vec<Usage>,
+    arguments: Vec<Argument>,
+    examples: &'static [&'static str],
+    permissions: Permissions,
+    access: Access,
+    cooldown: Option<Duration>,
+    guild_cooldown: Option<Duration>,
+}
+
+impl Entry {
+    fn new(handler: &dyn SlashCommand, language: Language) -> Self {
+        let builder = serde_json::to_value(handler.register()).unwrap_or_default();
+        let name = localized(&builder, "name", language);
+        let kind = builder["type"].as_u64();
+        let context_menu = matches!(kind, Some(USER_COMMAND | MESSAGE_COMMAND));
+
+        let (title, description, usages) = if context_menu {
+            let target = match (kind, language) {
+                (Some(MESSAGE_COMMAND), Language::English) => "a message",
+                (Some(MESSAGE_COMMAND), Language::Japanese) => "メッセージ",
+                (_, Language::English) => "a user",
+                (_, Language::Japanese) => "ユーザー",
+            };
+            let hint = match language {
+                Language::English => format!("Right-click {} → Apps → {}", target, name),
+                Language::Japanese => format!("{}を右クリック → アプリ → {}", target, name),
+            };
+            let usage = Usage { line: hint.clone(), description: String::new() };
+            (name, hint, vec![usage])
+        } else {
+            let description = localized(&builder, "description", language);
+            let usages = usages(&format!("/{}", name), &description, options(&builder), language);
+            (format!("/{}", name), description, usages)
+        };
+
+        let mut arguments = Vec::new();
+        collect_arguments(options(&builder), language, &mut arguments);
+
+        Entry {
+            id: handler.name(),
+            title,
+            context_menu,
+            description,
+            usages,
+            arguments,
+            examples: handler.examples(),
+            permissions: handler.required_permissions(),
+            access: handler.access(),
+            cooldown: handler.cooldown(),
+            guild_cooldown: handler.guild_cooldown(),
+        }
+    }
+
+    /// Whether not everyone can run the command.
+    fn restricted(&self) -> bool {
+        !self.permissions.is_empty() || self.access != Access::Everyone
+    }
+}
+
+/// Every command, slash commands by name first, then context menu commands.
+fn entries(language: Language) -> Vec<Entry> {
+    let mut entries = REGISTRY.commands().map(|handler| Entry::new(handler, language)).collect::<Vec<_>>();
+    entries.sort_by(|a, b| (a.context_menu, &a.title).cmp(&(b.context_menu, &b.title)));
+    entries
+}
+
+/// `value[key]`, or its Japanese localization in Japanese guilds.
+fn localized(value: &Value, key: &str, language: Language) -> String {
+    let localization = match language {
+        Language::English => None,
+        Language::Japanese => value[format!("{}_localizations", key).as_str()]["ja"].as_str(),
+    };
+    localization.or_else(|| value[key].as_str()).unwrap_or_default().to_string()
+}
+
+fn options(value: &Value) -> &[Value] {
+    value["options"].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn is_subcommand(option: &Value) -> bool {
+    matches!(option["type"].as_u64(), Some(SUB_COMMAND | SUB_COMMAND_GROUP))
+}
+
+/// The invocations of `prefix` with `options`: one per subcommand, or a
+/// single one listing the arguments, optional ones in brackets.
+fn usages(prefix: &str, description: &str, options: &[Value], language: Language) -> Vec<Usage> {
+    if !options.iter().any(is_subcommand) {
+        let arguments = options
+            .iter()
+            .map(|option| {
+                let name = option["name"].as_str().unwrap_or_default();
+                if option["required"].as_bool().unwrap_or(false) {
+                    format!(" {}:<{}>", name, name)
+                } else {
+                    format!(" [{}:<{}>]", name, name)
+                }
+            })
+            .collect::<String>();
+        return vec![Usage { line: format!("{}{}", prefix, arguments), description: description.to_string() }];
+    }
+
+    options
+        .iter()
+        .filter(|option| is_subcommand(option))
+        .flat_map(|subcommand| {
+            let prefix = format!("{} {}", prefix, subcommand["name"].as_str().unwrap_or_default());
+            let description = localized(subcommand, "description", language);
+            usages(&prefix, &description, self::options(subcommand), language)
+        })
+        .collect()
+}
+
+/// Every option below `options`, once per name and description.
+fn collect_arguments(options: &[Value], language: Language, arguments: &mut Vec<Argument>) {
+    for option in options {
+        if is_subcommand(option) {
+            collect_arguments(self::options(option), language, arguments);
+            continue;
+        }
+        let argument = Argument {
+            name: localized(option, "name", language),
+            description: localized(option, "description", language),
+            required: option["required"].as_bool().unwrap_or(false),
+            choices: option["choices"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|choice| localized(choice, "name", language))
+                .collect(),
+        };
+        if !arguments.iter().any(|a| a.name == argument.name && a.description == argument.description) {
+            arguments.push(argument);
+        }
+    }
+}
+
+/// `text`, cut to `max` characters.
+fn truncate(text: String, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text;
+    }
+    let mut truncated = text.chars().take(max - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+fn page_count(entries: usize) -> usize {
+    entries.div_ceil(PER_PAGE).max(1)
+}
+
+/// What a component of the help message does.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Show the list's page.
+    Page(usize),
+    /// Show the selected command, coming from the list's page.
+    Select(usize),
+}
+
+impl Action {
+    fn encode(&self) -> String {
+        match self {
+            Action::Page(page) => format!("help:page:{}", page),
+            Action::Select(page) => format!("help:select:{}", page),
+        }
+    }
+
+    fn parse(custom_id: &str) -> Result<Self, BotError> {
+        let malformed = || BotError::Internal(format!("malformed help custom ID {:?}", custom_id));
+        let mut parts = custom_id.split(':');
+        if parts.next() != Some("help") {
+            return Err(malformed());
+        }
+        let action = parts.next();
+        let page = parts.next().and_then(|page| page.parse::<usize>().ok()).ok_or_else(malformed)?;
+        if parts.next().is_some() {
+            return Err(malformed());
+        }
+        match action {
+            Some("page") => Ok(Action::Page(page)),
+            Some("select") => Ok(Action::Select(page)),
+            _ => Err(malformed()),
+        }
+    }
+}
+
+fn button(custom_id: String, label: &str) -> CreateButton {
+    CreateButton::new(custom_id).label(label).style(ButtonStyle::Secondary)
+}
+
+/// The list's `page`, clamped to the last page.
+fn list(entries: &[Entry], page: usize, language: Language) -> CreateInteractionResponseMessage {
+    let pages = page_count(entries.len());
+    let page = page.min(pages - 1);
+    let shown = &entries[page * PER_PAGE..((page + 1) * PER_PAGE).min(entries.len())];
+
+    let lines = shown
+        .iter()
+        .map(|entry| {
+            let lock = if entry.restricted() { " 🔒" } else { "" };
+            format!("**{}**{} — {}", entry.title, lock, entry.description)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let (title, footer, placeholder) = match language {
+        Language::English => (
+            "Commands",
+            format!("Page {}/{} · 🔒 not for everyone · Pick a command below for details", page + 1, pages),
+            "Show a command's details",
+        ),
+        Language::Japanese => (
+            "コマンド一覧",
+            format!("{}/{} ページ · 🔒 使える人が限られています · 下のメニューで詳細を表示します", page + 1, pages),
+            "コマンドの詳細を表示",
+        ),
+    };
+    let embed = CreateEmbed::new()
+        .title(title)
+        .description(truncate(lines, MAX_DESCRIPTION))
+        .footer(CreateEmbedFooter::new(footer));
+
+    let options = shown
+        .iter()
+        .map(|entry| {
+            let option = CreateSelectMenuOption::new(truncate(entry.title.clone(), MAX_OPTION_TEXT), entry.id);
+            if entry.description.is_empty() {
+                option
+            } else {
+                option.description(truncate(entry.description.clone(), MAX_OPTION_TEXT))
+            }
+        })
+        .collect();
+    let select = CreateSelectMenu::new(Action::Select(page).encode(), CreateSelectMenuKind::String { options })
+        .placeholder(placeholder);
+    let buttons = vec![
+        button(Action::Page(page.saturating_sub(1)).encode(), "◀").disabled(page == 0),
+        button("help:at".to_string(), &format!("{}/{}", page + 1, pages)).disabled(true),
+        button(Action::Page(page + 1).encode(), "▶").disabled(page + 1 >= pages),
+    ];
+
+    CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .components(vec![CreateActionRow::SelectMenu(select), CreateActionRow::Buttons(buttons)])
+}
+
+/// Who may run `entry`.
+fn audience(entry: &Entry, language: Language) -> String {
+    let mut lines = Vec::new();
+    if !entry.permissions.is_empty() {
+        let names = permission_names(entry.permissions, language);
+        lines.push(match language {
+            Language::English => format!("Members with {}", names),
+            Language::Japanese => format!("{} の権限を持つメンバー", names),
+        });
+    }
+    match (entry.access, language) {
+        (Access::Everyone, _) => {}
+        (Access::Manager, Language::English) => {
+            lines.push("TTS managers (Manage Server or the role set with /tts manager)".to_string())
+        }
+        (Access::Manager, Language::Japanese) => {
+            lines.push("TTS 管理者（サーバー管理の権限か /tts manager で設定したロール）".to_string())
+        }
+        (Access::Owner, Language::English) => lines.push("The bot's owners".to_string()),
+        (Access::Owner, Language::Japanese) => lines.push("Bot のオーナー".to_string()),
+    }
+    if lines.is_empty() {
+        lines.push(match language {
+            Language::English => "Everyone".to_string(),
+            Language::Japanese => "全員".to_string(),
+        });
+    }
+    lines.join("\n")
+}
+
+fn cooldowns(entry: &Entry, language: Language) -> Option<String> {
+    let mut lines = Vec::new();
+    if let Some(cooldown) = entry.cooldown {
+        lines.push(match language {
+            Language::English => format!("{}s per user", cooldown.as_secs()),
+            Language::Japanese => format!("ユーザーごとに {} 秒", cooldown.as_secs()),
+        });
+    }
+    if let Some(cooldown) = entry.guild_cooldown {
+        lines.push(match language {
+            Language::English => format!("{}s per server", cooldown.as_secs()),
+            Language::Japanese => format!("サーバーごとに {} 秒", cooldown.as_secs()),
+        });
+    }
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// `entry`'s page, with a button back to the list's `page`.
+fn detail(entry: &Entry, page: usize, language: Language) -> CreateInteractionResponseMessage {
+    let japanese = matches!(language, Language::Japanese);
+    let label = |english: &'static str, japanese_label: &'static str| if japanese { japanese_label } else { english };
+
+    let usage = entry
+        .usages
+        .iter()
+        .map(|usage| {
+            if entry.usages.len() > 1 && !usage.description.is_empty() {
+                format!("`{}`\n{}", usage.line, usage.description)
+            } else {
+                format!("`{}`", usage.line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let description = if entry.context_menu {
+        entry.description.clone()
+    } else {
+        format!("{}\n\n**{}**\n{}", entry.description, label("Usage", "使い方"), usage)
+    };
+
+    let mut embed = CreateEmbed::new().title(&entry.title).description(truncate(description, MAX_DESCRIPTION));
+    if !entry.arguments.is_empty() {
+        let arguments = entry
+            .arguments
+            .iter()
+            .map(|argument| {
+                let mut line = format!("`{}` — {}", argument.name, argument.description);
+                if !argument.choices.is_empty() {
+                    line.push_str(&format!(" ({})", argument.choices.join(", ")));
+                }
+                if !argument.required {
+                    line.push_str(label(" (optional)", "（任意）"));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        embed = embed.field(label("Options", "オプション"), truncate(arguments, MAX_FIELD), false);
+    }
+    if !entry.examples.is_empty() {
+        let examples = entry.examples.iter().map(|example| format!("`{}`", example)).collect::<Vec<_>>().join("\n");
+        embed = embed.field(label("Examples", "例"), truncate(examples, MAX_FIELD), false);
+    }
+    embed = embed.field(label("Who can use it", "使える人"), audience(entry, language), true);
+    if let Some(cooldowns) = cooldowns(entry, language) {
+        embed = embed.field(label("Cooldown", "クールダウン"), cooldowns, true);
+    }
+
+    let back = button(Action::Page(page).encode(), label("◀ All commands", "◀ コマンド一覧"));
+    CreateInteractionResponseMessage::new()
+        .embed(embed)
+        .components(vec![CreateActionRow::Buttons(vec![back])])
+}
+
+/// The entry `name` refers to, by dispatch name or title.
+fn find<'a>(entries: &'a [Entry], name: &str) -> Option<(usize, &'a Entry)> {
+    let name = name.trim();
+    entries.iter().enumerate().find(|(_, entry)| {
+        entry.id.eq_ignore_ascii_case(name.trim_start_matches('/')) || entry.title.eq_ignore_ascii_case(name)
+    })
+}
+
+fn unknown_command() -> BotError {
+    BotError::invalid("There is no such command, pick one from the list", "そのコマンドはありません。一覧から選んでください")
+}
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<CreateInteractionResponseMessage, BotError> {
+    let language = guild_language(ctx, command.guild_id).await;
+    let entries = entries(language);
+    let requested = command.data.options().into_iter().find_map(|option| match option.value {
+        ResolvedValue::String(name) if option.name == "command" => Some(name),
+        _ => None,
+    });
+    Ok(match requested {
+        Some(name) => {
+            let (index, entry) = find(&entries, name).ok_or_else(unknown_command)?;
+            detail(entry, index / PER_PAGE, language)
+        }
+        None => list(&entries, 0, language),
+    })
+}
+
+async fn on_component(ctx: &Context, interaction: &ComponentInteraction) -> Result<(), BotError> {
+    let language = guild_language(ctx, interaction.guild_id).await;
+    let entries = entries(language);
+    let message = match Action::parse(&interaction.data.custom_id)? {
+        Action::Page(page) => list(&entries, page, language),
+        Action::Select(page) => {
+            let selected = match &interaction.data.kind {
+                ComponentInteractionDataKind::StringSelect { values } => values.first(),
+                _ => None,
+            };
+            let selected = selected.ok_or_else(|| BotError::Internal("help select menu sent no value".to_string()))?;
+            let (_, entry) = find(&entries, selected).ok_or_else(unknown_command)?;
+            detail(entry, page, language)
+        }
+    };
+    interaction.create_response(&ctx.http, CreateInteractionResponse::UpdateMessage(message)).await?;
+    Ok(())
+}
+
+pub struct Help;
+
+#[async_trait]
+impl SlashCommand for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("List the bot's commands and how to use them")
+            .description_localized("ja", "コマンドの一覧と使い方を表示します")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "command", "Show the details of one command")
+                    .description_localized("ja", "指定したコマンドの詳細を表示します")
+                    .set_autocomplete(true),
+            )
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["/help", "/help command:dict"]
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        let message = run(command, ctx).await?.ephemeral(true);
+        command.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await?;
+        Ok(Reply::Responded)
+    }
+
+    fn ephemeral(&self) -> bool {
+        true
+    }
+
+    /// Suggests the commands for `/help command`
+    async fn autocomplete(&self, ctx: &Context, command: &CommandInteraction) -> Result<Vec<AutocompleteChoice>, BotError> {
+        let Some(focused) = command.data.autocomplete() else {
+            return Ok(Vec::new());
+        };
+        let language = guild_language(ctx, command.guild_id).await;
+        let candidates = entries(language).into_iter().map(|entry| Candidate::new(entry.title, entry.id));
+        Ok(autocomplete::choices(focused.value, candidates))
+    }
+
+    async fn component(&self, ctx: &Context, interaction: &ComponentInteraction) -> Result<(), BotError> {
+        on_component(ctx, interaction).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(command: CreateCommand) -> Value {
+        serde_json::to_value(command).unwrap()
+    }
+
+    #[test]
+    fn usages_cover_every_subcommand() {
+        let dict = builder(
+            CreateCommand::new("dict")
+                .description("Dictionary")
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Add a word")
+                        .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "word", "Word").required(true))
+                        .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "note", "Note")),
+                )
+                .add_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommandGroup, "skip", "Skip prefixes").add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::SubCommand, "list", "List them"),
+                    ),
+                ),
+        );
+        let lines = usages("/dict", "Dictionary", options(&dict), Language::English)
+            .into_iter()
+            .map(|usage| (usage.line, usage.description))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                ("/dict add word:<word> [note:<note>]".to_string(), "Add a word".to_string()),
+                ("/dict skip list".to_string(), "List them".to_string()),
+            ]
+        );
+
+        let ping = builder(CreateCommand::new("ping").description("Ping"));
+        assert_eq!(usages("/ping", "Ping", options(&ping), Language::English)[0].line, "/ping");
+    }
+
+    #[test]
+    fn descriptions_are_localized() {
+        let command = builder(CreateCommand::new("ping").description("Ping").description_localized("ja", "ピン"));
+        assert_eq!(localized(&command, "description", Language::English), "Ping");
+        assert_eq!(localized(&command, "description", Language::Japanese), "ピン");
+        assert_eq!(localized(&command, "name", Language::Japanese), "ping");
+
+        for entry in entries(Language::Japanese) {
+            assert!(!entry.description.is_empty(), "{} has no description", entry.title);
+        }
+    }
+
+    #[test]
+    fn custom_ids_round_trip() {
+        for action in [Action::Page(0), Action::Page(12), Action::Select(3)] {
+            assert_eq!(Action::parse(&action.encode()).unwrap(), action);
+        }
+        assert!(Action::parse("help:page").is_err());
+        assert!(Action::parse("help:open:1").is_err());
+        assert!(Action::parse("settings:page:1").is_err());
+    }
+
+    #[test]
+    fn pages_hold_every_command() {
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(PER_PAGE), 1);
+        assert_eq!(page_count(PER_PAGE + 1), 2);
+
+        let entries = entries(Language::English);
+        let (index, entry) = find(&entries, "/dict").unwrap();
+        assert_eq!(entry.id, "dict");
+        assert_eq!(find(&entries, "Read aloud").map(|(_, entry)| entry.id), Some("Read aloud"));
+        assert!(index / PER_PAGE < page_count(entries.len()));
+        assert!(entries.last().unwrap().context_menu);
+    }
+}
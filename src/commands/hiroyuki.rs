@@ -1,38 +1,58 @@
-use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::async_trait;
+use serenity::builder::{CreateAttachment, CreateCommand, CreateCommandOption};
 use serenity::model::application::{CommandOptionType, ResolvedOption, ResolvedValue, CommandInteraction};
 use serenity::prelude::*;
-use crate::api::hiroyuki::get_hiroyuki_voice;
-
-pub async fn run(ctx: &Context, command: &CommandInteraction, options: &[ResolvedOption<'_>]) -> Result<Vec<u8>, String> {
-    // First, defer the response to show that we're processing
-    if let Err(why) = command.defer(&ctx.http).await {
-        println!("❌ Failed to defer response: {}", why);
-        return Err(format!("Failed to defer response: {}", why));
-    }
-    println!("✅ Successfully deferred response");
+use std::time::Duration;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::tts::{self, voices};
+use tracing::debug;
 
+async fn run(options: &[ResolvedOption<'_>]) -> Result<Vec<u8>, BotError> {
     if let Some(ResolvedOption {
         value: ResolvedValue::String(text), ..
     }) = options.first()
     {
-        println!("🎤 Processing voice command for text: {}", text);
-        get_hiroyuki_voice(text)
-            .await
-            .map_err(|e| {
-                println!("❌ Error generating voice: {}", e);
-                format!("Failed to generate Hiroyuki's voice: {}", e)
-            })
+        debug!(text = %crate::logging::content(text), "Processing voice command");
+        // Goes through the cache and circuit breaker like any other speech
+        let audio = tts::synthesize(&voices::find(None), text).await.map_err(BotError::Backend)?;
+        Ok(audio.as_ref().clone())
     } else {
-        println!("❌ No valid text provided");
-        Err("Please provide valid text".into())
+        Err(BotError::invalid("Please provide valid text", "テキストを入力してください"))
     }
 }
 
-pub fn register() -> CreateCommand {
-    CreateCommand::new("hiroyuki")
-        .description("Convert text to Hiroyuki's voice")
-        .add_option(
-            CreateCommandOption::new(CommandOptionType::String, "text", "The text to convert to speech")
-                .required(true),
-        )
-}
\ No newline at end of file
+pub struct Hiroyuki;
+
+#[async_trait]
+impl SlashCommand for Hiroyuki {
+    fn name(&self) -> &'static str {
+        "hiroyuki"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Convert text to Hiroyuki's voice")
+            .description_localized("ja", "テキストをひろゆきの声に変換します")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "text", "The text to convert to speech")
+                    .required(true),
+            )
+    }
+
+    async fn execute(&self, _ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        let audio = run(&command.data.options()).await?;
+        Ok(Reply::Message {
+            content: "✅ Voice generated successfully!".to_string(),
+            attachments: vec![CreateAttachment::bytes(audio, "hiroyuki.wav")],
+        })
+    }
+
+    /// Every use is a request to the TTS API
+    fn cooldown(&self) -> Option<Duration> {
+        Some(Duration::from_secs(10))
+    }
+
+    fn defers(&self) -> bool {
+        true
+    }
+}
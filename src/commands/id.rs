@@ -1,7 +1,11 @@
 use serenity::builder::{CreateCommand, CreateCommandOption};
-use serenity::model::application::{CommandOptionType, ResolvedOption, ResolvedValue};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue};
+use serenity::async_trait;
+use serenity::prelude::*;
 
-pub fn run(options: &[ResolvedOption]) -> String {
+use crate::commands::{BotError, Reply, SlashCommand};
+
+fn run(options: &[ResolvedOption]) -> String {
     if let Some(ResolvedOption {
         value: ResolvedValue::User(user, _), ..
     }) = options.first()
@@ -12,9 +16,25 @@ pub fn run(options: &[ResolvedOption]) -> String {
     }
 }
 
-pub fn register() -> CreateCommand {
-    CreateCommand::new("id").description("Get a user id").add_option(
-        CreateCommandOption::new(CommandOptionType::User, "id", "The user to lookup")
-            .required(true),
-    )
-}
\ No newline at end of file
+pub struct Id;
+
+#[async_trait]
+impl SlashCommand for Id {
+    fn name(&self) -> &'static str {
+        "id"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Get a user id")
+            .description_localized("ja", "ユーザーの ID を表示します")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::User, "id", "The user to lookup")
+                    .required(true),
+            )
+    }
+
+    async fn execute(&self, _ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        Ok(Reply::from(run(&command.data.options())))
+    }
+}
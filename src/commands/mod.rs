@@ -1,10 +1,59 @@
 pub mod attachmentinput;
 pub mod id;
-pub mod modal;
 pub mod numberinput;
-pub mod ping;
+pub mod status;
 pub mod welcome;
 pub mod wonderful_command;
 pub mod hiroyuki;
 pub mod voice;
-pub mod setlanguage;
\ No newline at end of file
+pub mod setlanguage;
+pub mod nameannounce;
+pub mod callme;
+pub mod dict;
+pub mod readsettings;
+pub mod voiceannounce;
+pub mod tts;
+pub mod shards;
+pub mod settings;
+pub mod voices;
+pub mod readaloud;
+pub mod help;
+pub mod registry;
+pub mod error;
+pub mod sync;
+pub mod autocomplete;
+pub mod access;
+
+use lazy_static::lazy_static;
+
+pub use access::Access;
+pub use error::BotError;
+pub use registry::{Registry, Reply, SlashCommand};
+
+lazy_static! {
+    /// Every slash and context menu command, in registration order.
+    pub static ref REGISTRY: Registry = Registry::new(vec![
+        Box::new(status::Status),
+        Box::new(id::Id),
+        Box::new(welcome::Welcome),
+        Box::new(numberinput::NumberInput),
+        Box::new(attachmentinput::AttachmentInput),
+        Box::new(wonderful_command::WonderfulCommand),
+        Box::new(hiroyuki::Hiroyuki),
+        Box::new(voice::Connect),
+        Box::new(voice::Disconnect),
+        Box::new(voice::Unbind),
+        Box::new(setlanguage::SetLanguage),
+        Box::new(nameannounce::NameAnnounceCommand),
+        Box::new(callme::CallMe),
+        Box::new(dict::Dict),
+        Box::new(readsettings::ReadSettingsCommand),
+        Box::new(voiceannounce::VoiceAnnounce),
+        Box::new(tts::Tts),
+        Box::new(shards::Shards),
+        Box::new(settings::Settings),
+        Box::new(voices::VoiceCommand),
+        Box::new(readaloud::ReadAloud),
+        Box::new(help::Help),
+    ]);
+}
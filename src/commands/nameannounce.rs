@@ -0,0 +1,65 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::prelude::*;
+use serenity::async_trait;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+use crate::db::name_announce::NameAnnounce;
+use tracing::debug;
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let store = crate::db::store(ctx).await;
+
+    let mode = command
+        .data
+        .options
+        .first()
+        .and_then(|option| option.value.as_str())
+        .map(NameAnnounce::from)
+        .ok_or_else(|| {
+            BotError::invalid(
+                "Please provide a valid mode (always/change/never)",
+                "モードを選んでください (always/change/never)",
+            )
+        })?;
+
+    debug!("Setting name announce mode for guild {} to {:?}", guild_id, mode);
+    store.set_name_announce(guild_id.get(), mode).await?;
+
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+    Ok(match (language, mode) {
+        (Language::English, NameAnnounce::Always) => "Author names will be read before every message".to_string(),
+        (Language::English, NameAnnounce::OnChange) => "Author names will be read when the speaker changes".to_string(),
+        (Language::English, NameAnnounce::Never) => "Author names will not be read".to_string(),
+        (Language::Japanese, NameAnnounce::Always) => "すべてのメッセージの前に名前を読み上げます".to_string(),
+        (Language::Japanese, NameAnnounce::OnChange) => "話者が変わったときに名前を読み上げます".to_string(),
+        (Language::Japanese, NameAnnounce::Never) => "名前を読み上げません".to_string(),
+    })
+}
+
+pub struct NameAnnounceCommand;
+
+#[async_trait]
+impl SlashCommand for NameAnnounceCommand {
+    fn name(&self) -> &'static str {
+        "nameannounce"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Choose when the author's name is read before their message")
+            .description_localized("ja", "メッセージの前に名前を読み上げるタイミングを選びます")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "mode", "When to read the author's name")
+                    .required(true)
+                    .add_string_choice("Always", "always")
+                    .add_string_choice("When the speaker changes", "change")
+                    .add_string_choice("Never", "never"),
+            )
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+}
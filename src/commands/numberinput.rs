@@ -1,23 +1,41 @@
 use serenity::builder::{CreateCommand, CreateCommandOption};
-use serenity::model::application::CommandOptionType;
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::async_trait;
+use serenity::prelude::*;
 
-pub fn register() -> CreateCommand {
-    CreateCommand::new("numberinput")
-        .description("Test command for number input")
-        .add_option(
-            CreateCommandOption::new(CommandOptionType::Integer, "int", "An integer from 5 to 10")
-                .min_int_value(5)
-                .max_int_value(10)
+use crate::commands::{BotError, Reply, SlashCommand};
+
+pub struct NumberInput;
+
+#[async_trait]
+impl SlashCommand for NumberInput {
+    fn name(&self) -> &'static str {
+        "numberinput"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Test command for number input")
+            .description_localized("ja", "数値入力のテスト")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Integer, "int", "An integer from 5 to 10")
+                    .min_int_value(5)
+                    .max_int_value(10)
+                    .required(true),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Number,
+                    "number",
+                    "A float from -3.3 to 234.5",
+                )
+                .min_number_value(-3.3)
+                .max_number_value(234.5)
                 .required(true),
-        )
-        .add_option(
-            CreateCommandOption::new(
-                CommandOptionType::Number,
-                "number",
-                "A float from -3.3 to 234.5",
             )
-            .min_number_value(-3.3)
-            .max_number_value(234.5)
-            .required(true),
-        )
-}
\ No newline at end of file
+    }
+
+    async fn execute(&self, _ctx: &Context, _command: &CommandInteraction) -> Result<Reply, BotError> {
+        Ok(Reply::from("not implemented :("))
+    }
+}
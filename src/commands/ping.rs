@@ -1,10 +0,0 @@
-use serenity::builder::CreateCommand;
-use serenity::model::application::ResolvedOption;
-
-pub fn run(_options: &[ResolvedOption]) -> String {
-    "Hey, I'm alive!".to_string()
-}
-
-pub fn register() -> CreateCommand {
-    CreateCommand::new("ping").description("A ping command")
-}
\ No newline at end of file
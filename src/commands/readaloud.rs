@@ -0,0 +1,89 @@
+use serenity::builder::CreateCommand;
+use serenity::model::application::{CommandInteraction, CommandType, ResolvedTarget};
+use serenity::prelude::*;
+use serenity::async_trait;
+use std::time::Duration;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+use crate::handlers::message::{self, Outcome, ReadError};
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let Some(ResolvedTarget::Message(target)) = command.data.target() else {
+        return Err(BotError::Internal("Read aloud was used without a target message".to_string()));
+    };
+
+    // Only read into a call the invoker is part of, so nobody can make the
+    // bot talk in a channel they aren't listening to
+    let user_channel = guild_id
+        .to_guild_cached(&ctx.cache)
+        .and_then(|guild| guild.voice_states.get(&command.user.id).and_then(|state| state.channel_id))
+        .ok_or(BotError::NotInVoiceChannel)?;
+    let manager = songbird::get(ctx)
+        .await
+        .ok_or_else(|| BotError::Internal("songbird is not registered with the client".to_string()))?;
+    let bot_channel = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.current_channel(),
+        None => None,
+    };
+    if bot_channel.map(|channel| channel.0.get()) != Some(user_channel.get()) {
+        return Err(BotError::invalid(
+            "I'm not in your voice channel, use /connect first",
+            "ボイスチャンネルに参加していません。先に /connect を使ってください",
+        ));
+    }
+
+    let language = crate::db::store(ctx).await.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+    let target = message::with_member(ctx, guild_id, target).await;
+    let outcome = message::read(ctx, guild_id, &target).await.map_err(|e| match e {
+        ReadError::Database(e) => BotError::Database(e),
+        ReadError::Speech(e) => BotError::Backend(e),
+    })?;
+    let reply = match (outcome, language) {
+        (Outcome::Queued, Language::English) => "Reading the message aloud",
+        (Outcome::Queued, Language::Japanese) => "メッセージを読み上げます",
+        (Outcome::OptedOut, Language::English) => "The author has turned off reading their messages",
+        (Outcome::OptedOut, Language::Japanese) => "投稿者がメッセージの読み上げをオフにしています",
+        (Outcome::Ignored, Language::English) => "That message is on the server's ignore list",
+        (Outcome::Ignored, Language::Japanese) => "このメッセージはサーバーの無視リストに含まれています",
+        (Outcome::Skipped, Language::English) => "That message starts with a skip prefix",
+        (Outcome::Skipped, Language::Japanese) => "このメッセージはスキップする接頭辞で始まっています",
+        (Outcome::Empty, Language::English) => "There is nothing to read in that message",
+        (Outcome::Empty, Language::Japanese) => "このメッセージには読み上げる内容がありません",
+        (Outcome::NotConnected, Language::English) => "I'm leaving the voice channel, the message wasn't read",
+        (Outcome::NotConnected, Language::Japanese) => "ボイスチャンネルから退出中のため読み上げませんでした",
+    };
+    Ok(reply.to_string())
+}
+
+pub struct ReadAloud;
+
+#[async_trait]
+impl SlashCommand for ReadAloud {
+    fn name(&self) -> &'static str {
+        "Read aloud"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .kind(CommandType::Message)
+            .name_localized("ja", "読み上げ")
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+
+    fn cooldown(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+
+    fn ephemeral(&self) -> bool {
+        true
+    }
+
+    /// Synthesis can take longer than Discord waits for a response
+    fn defers(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,91 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::prelude::*;
+use serenity::async_trait;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+use crate::db::ReadSettings;
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let store = crate::db::store(ctx).await;
+
+    let mut settings = store.get_read_settings(guild_id.get()).await?;
+
+    let options = command.data.options();
+    for option in &options {
+        if let ResolvedValue::Boolean(enabled) = option.value {
+            match option.name {
+                "replies" => settings.replies = enabled,
+                "attachments" => settings.attachments = enabled,
+                "stickers" => settings.stickers = enabled,
+                "embeds" => settings.embeds = enabled,
+                "polls" => settings.polls = enabled,
+                _ => {}
+            }
+        }
+    }
+
+    if !options.is_empty() {
+        store.set_read_settings(guild_id.get(), settings).await?;
+    }
+
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+    Ok(describe(language, &settings))
+}
+
+/// The option names, in the order of [`labels`] and [`values`].
+pub const KEYS: [&str; 5] = ["replies", "attachments", "stickers", "embeds", "polls"];
+
+pub fn labels(language: Language) -> [&'static str; 5] {
+    match language {
+        Language::English => ["Replies", "Attachments", "Stickers", "Embed titles", "Polls"],
+        Language::Japanese => ["返信", "添付ファイル", "スタンプ", "埋め込みのタイトル", "投票"],
+    }
+}
+
+pub fn values(settings: &ReadSettings) -> [bool; 5] {
+    [settings.replies, settings.attachments, settings.stickers, settings.embeds, settings.polls]
+}
+
+pub fn describe(language: Language, settings: &ReadSettings) -> String {
+    let mark = |enabled: bool| if enabled { "✅" } else { "❌" };
+
+    labels(language)
+        .iter()
+        .zip(values(settings))
+        .map(|(label, enabled)| format!("{} {}", mark(enabled), label))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub struct ReadSettingsCommand;
+
+#[async_trait]
+impl SlashCommand for ReadSettingsCommand {
+    fn name(&self) -> &'static str {
+        "readsettings"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Choose which parts of a message are read aloud (no options shows the current settings)")
+            .description_localized("ja", "メッセージのどの部分を読み上げるか選びます（オプションなしで現在の設定を表示）")
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "replies", "Say who a reply is addressed to"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "attachments", "Announce attachments by type and count"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "stickers", "Read sticker names"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "embeds", "Read embed titles"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "polls", "Summarize polls"))
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &[
+            "/readsettings attachments:false embeds:true",
+            "/readsettings",
+        ]
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+}
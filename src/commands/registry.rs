@@ -0,0 +1,473 @@
+//! The [`SlashCommand`] trait and the [`Registry`] that drives both command
+//! registration and dispatch.
+//!
+//! Every interaction goes through [`Registry::dispatch`], which checks the
+//! command's permissions, [`Access`] and cooldowns, defers if the command asks for it,
+//! runs it and answers with its [`Reply`], or with the [`BotError`]'s
+//! localized message if it fails.
+//!
+//! Components and modals belong to the command whose name prefixes their
+//! custom ID (`<name>:…`), and are routed to its [`SlashCommand::component`]
+//! and [`SlashCommand::modal`] by [`Registry::dispatch_component`] and
+//! [`Registry::dispatch_modal`] after the same permission and access checks.
+//! Autocomplete requests go to [`SlashCommand::autocomplete`] through
+//! [`Registry::dispatch_autocomplete`].
+
+use serenity::async_trait;
+use serenity::builder::{
+    AutocompleteChoice, CreateAllowedMentions, CreateAttachment, CreateAutocompleteResponse, CreateCommand,
+    CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, EditInteractionResponse,
+};
+use serenity::model::application::{CommandInteraction, ComponentInteraction, ModalInteraction};
+use serenity::model::guild::Member;
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::{error, Instrument};
+
+use super::access::{self, Access};
+use super::BotError;
+use crate::db::language::Language;
+
+/// What a command answers with.
+pub enum Reply {
+    /// A message, optionally with files.
+    Message {
+        content: String,
+        attachments: Vec<CreateAttachment>,
+    },
+    /// The command already responded to the interaction itself.
+    Responded,
+}
+
+impl From<String> for Reply {
+    fn from(content: String) -> Self {
+        Reply::Message { content, attachments: Vec::new() }
+    }
+}
+
+impl From<&str> for Reply {
+    fn from(content: &str) -> Self {
+        Reply::from(content.to_string())
+    }
+}
+
+#[async_trait]
+pub trait SlashCommand: Send + Sync {
+    /// The name the command is registered and dispatched under.
+    fn name(&self) -> &'static str;
+
+    /// Builds the command for registration with Discord.
+    fn register(&self) -> CreateCommand;
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError>;
+
+    /// Example invocations shown on the command's `/help` page.
+    fn examples(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Permissions the invoking member needs. Also hides the command from
+    /// members without them.
+    fn required_permissions(&self) -> Permissions {
+        Permissions::empty()
+    }
+
+    /// Who may run the command besides [`Self::required_permissions`].
+    fn access(&self) -> Access {
+        Access::Everyone
+    }
+
+    /// How long a user has to wait between two uses of the command.
+    fn cooldown(&self) -> Option<Duration> {
+        None
+    }
+
+    /// How long a guild has to wait between two uses of the command, by
+    /// anyone.
+    fn guild_cooldown(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Whether the reply is only shown to the user who ran the command.
+    fn ephemeral(&self) -> bool {
+        false
+    }
+
+    /// Whether to defer before executing, for commands that may take longer
+    /// than the three seconds Discord waits for a response.
+    fn defers(&self) -> bool {
+        false
+    }
+
+    /// Suggests values for the option being typed, see
+    /// [`CommandInteraction::autocomplete`]. Only called for options
+    /// registered with `set_autocomplete(true)`.
+    async fn autocomplete(&self, _ctx: &Context, _command: &CommandInteraction) -> Result<Vec<AutocompleteChoice>, BotError> {
+        Ok(Vec::new())
+    }
+
+    /// Handles a click or selection on one of the command's components. The
+    /// handler responds to the interaction itself.
+    async fn component(&self, _ctx: &Context, interaction: &ComponentInteraction) -> Result<(), BotError> {
+        Err(BotError::Internal(format!("/{} has no component {}", self.name(), interaction.data.custom_id)))
+    }
+
+    /// Handles the submission of one of the command's modals. The handler
+    /// responds to the interaction itself.
+    async fn modal(&self, _ctx: &Context, interaction: &ModalInteraction) -> Result<(), BotError> {
+        Err(BotError::Internal(format!("/{} has no modal {}", self.name(), interaction.data.custom_id)))
+    }
+}
+
+/// Who a cooldown applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bucket {
+    User(UserId),
+    Guild(GuildId),
+}
+
+/// Remembers until when each command is cooling down for each bucket.
+#[derive(Default)]
+pub struct Cooldowns {
+    until: HashMap<(&'static str, Bucket), Instant>,
+}
+
+impl Cooldowns {
+    /// How long `bucket` still has to wait before using `command` again.
+    pub fn remaining(&self, command: &'static str, bucket: Bucket, now: Instant) -> Option<Duration> {
+        self.until
+            .get(&(command, bucket))
+            .and_then(|until| until.checked_duration_since(now))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Records a use of `command` by `bucket` at `now`.
+    pub fn start(&mut self, command: &'static str, bucket: Bucket, cooldown: Duration, now: Instant) {
+        // Forget expired entries so the map doesn't grow with every user
+        self.until.retain(|_, until| *until > now);
+        self.until.insert((command, bucket), now + cooldown);
+    }
+}
+
+pub struct Registry {
+    commands: Vec<Box<dyn SlashCommand>>,
+    cooldowns: Mutex<Cooldowns>,
+}
+
+impl Registry {
+    pub fn new(commands: Vec<Box<dyn SlashCommand>>) -> Self {
+        Self { commands, cooldowns: Mutex::new(Cooldowns::default()) }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn SlashCommand> {
+        self.commands.iter().find(|command| command.name() == name).map(|command| command.as_ref())
+    }
+
+    /// Every command, in registration order.
+    pub fn commands(&self) -> impl Iterator<Item = &dyn SlashCommand> {
+        self.commands.iter().map(|command| command.as_ref())
+    }
+
+    /// Every command's registration builder.
+    pub fn builders(&self) -> Vec<CreateCommand> {
+        self.commands
+            .iter()
+            .map(|command| {
+                let permissions = command.required_permissions();
+                if permissions.is_empty() {
+                    command.register()
+                } else {
+                    command.register().default_member_permissions(permissions)
+                }
+            })
+            .collect()
+    }
+
+    /// Runs the invoked command and answers the interaction, even if the
+    /// command fails or panics.
+    pub async fn dispatch(&'static self, ctx: &Context, command: &CommandInteraction) {
+        let mut deferred = false;
+        let result = self.run(ctx, command, &mut deferred).await;
+        match result {
+            Ok(reply) => {
+                let ephemeral = self.get(&command.data.name).is_some_and(|handler| handler.ephemeral());
+                respond(ctx, command, deferred, ephemeral, reply).await;
+            }
+            Err(e) => {
+                e.log();
+                let language = guild_language(ctx, command.guild_id).await;
+                respond_error(ctx, command, deferred, e.message(language)).await;
+            }
+        }
+    }
+
+    /// Answers an autocomplete request with the command's suggestions, or
+    /// with none if it fails.
+    pub async fn dispatch_autocomplete(&'static self, ctx: &Context, command: &CommandInteraction) {
+        let result = match self.get(&command.data.name) {
+            Some(handler) => {
+                let (ctx, command) = (ctx.clone(), command.clone());
+                tokio::spawn(async move { handler.autocomplete(&ctx, &command).await }.in_current_span())
+                    .await
+                    .map_err(|e| BotError::Internal(format!("/{} autocomplete panicked: {}", handler.name(), e)))
+                    .and_then(|result| result)
+            }
+            None => Err(BotError::Internal(format!("/{} is not in the registry", command.data.name))),
+        };
+        let choices = result.unwrap_or_else(|e| {
+            e.log();
+            Vec::new()
+        });
+        let response = CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new().set_choices(choices));
+        if let Err(why) = command.create_response(&ctx.http, response).await {
+            error!("Cannot send autocomplete suggestions: {why}");
+        }
+    }
+
+    /// Routes a component interaction to the command that created it.
+    pub async fn dispatch_component(&'static self, ctx: &Context, interaction: &ComponentInteraction) {
+        if let Err(e) = self.run_component(ctx, interaction).await {
+            e.log();
+            let language = guild_language(ctx, interaction.guild_id).await;
+            let response = CreateInteractionResponse::Message(error_message(e.message(language)));
+            if let Err(why) = interaction.create_response(&ctx.http, response).await {
+                error!("Cannot send error response: {why}");
+            }
+        }
+    }
+
+    /// Routes a modal submission to the command that opened the modal.
+    pub async fn dispatch_modal(&'static self, ctx: &Context, interaction: &ModalInteraction) {
+        if let Err(e) = self.run_modal(ctx, interaction).await {
+            e.log();
+            let language = guild_language(ctx, interaction.guild_id).await;
+            let response = CreateInteractionResponse::Message(error_message(e.message(language)));
+            if let Err(why) = interaction.create_response(&ctx.http, response).await {
+                error!("Cannot send error response: {why}");
+            }
+        }
+    }
+
+    /// The command whose name prefixes `custom_id`.
+    fn owner(&'static self, custom_id: &str) -> Result<&'static dyn SlashCommand, BotError> {
+        let name = custom_id.split(':').next().unwrap_or_default();
+        self.get(name)
+            .ok_or_else(|| BotError::Internal(format!("no command owns custom ID {:?}", custom_id)))
+    }
+
+    async fn run_component(&'static self, ctx: &Context, interaction: &ComponentInteraction) -> Result<(), BotError> {
+        let handler = self.owner(&interaction.data.custom_id)?;
+        let member = interaction.member.as_ref();
+        authorize(ctx, handler, interaction.guild_id, interaction.user.id, member).await?;
+
+        let (ctx, interaction) = (ctx.clone(), interaction.clone());
+        tokio::spawn(async move { handler.component(&ctx, &interaction).await }.in_current_span())
+            .await
+            .map_err(|e| BotError::Internal(format!("/{} component panicked: {}", handler.name(), e)))?
+    }
+
+    async fn run_modal(&'static self, ctx: &Context, interaction: &ModalInteraction) -> Result<(), BotError> {
+        let handler = self.owner(&interaction.data.custom_id)?;
+        let member = interaction.member.as_ref();
+        authorize(ctx, handler, interaction.guild_id, interaction.user.id, member).await?;
+
+        let (ctx, interaction) = (ctx.clone(), interaction.clone());
+        tokio::spawn(async move { handler.modal(&ctx, &interaction).await }.in_current_span())
+            .await
+            .map_err(|e| BotError::Internal(format!("/{} modal panicked: {}", handler.name(), e)))?
+    }
+
+    async fn run(&'static self, ctx: &Context, command: &CommandInteraction, deferred: &mut bool) -> Result<Reply, BotError> {
+        let handler = self
+            .get(&command.data.name)
+            .ok_or_else(|| BotError::Internal(format!("/{} is not in the registry", command.data.name)))?;
+        self.check(ctx, handler, command).await?;
+
+        if handler.defers() {
+            let data = CreateInteractionResponseMessage::new().ephemeral(handler.ephemeral());
+            command.create_response(&ctx.http, CreateInteractionResponse::Defer(data)).await?;
+            *deferred = true;
+        }
+
+        // Run the command in its own task so a panic becomes an error reply
+        // instead of an interaction stuck "thinking…"
+        let (ctx, command) = (ctx.clone(), command.clone());
+        tokio::spawn(async move { handler.execute(&ctx, &command).await }.in_current_span())
+            .await
+            .map_err(|e| BotError::Internal(format!("/{} panicked: {}", handler.name(), e)))?
+    }
+
+    /// Refuses the command if the user lacks permissions or access, or it is
+    /// cooling down. Cooldowns only start once every check passed.
+    async fn check(&self, ctx: &Context, handler: &dyn SlashCommand, command: &CommandInteraction) -> Result<(), BotError> {
+        authorize(ctx, handler, command.guild_id, command.user.id, command.member.as_deref()).await?;
+
+        let mut buckets = Vec::new();
+        if let (Some(cooldown), Some(guild_id)) = (handler.guild_cooldown(), command.guild_id) {
+            buckets.push((Bucket::Guild(guild_id), cooldown));
+        }
+        if let Some(cooldown) = handler.cooldown() {
+            buckets.push((Bucket::User(command.user.id), cooldown));
+        }
+        if buckets.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut cooldowns = self.cooldowns.lock().await;
+        for (bucket, _) in &buckets {
+            if let Some(remaining) = cooldowns.remaining(handler.name(), *bucket, now) {
+                return Err(match bucket {
+                    Bucket::User(_) => BotError::Cooldown(remaining),
+                    Bucket::Guild(_) => BotError::GuildCooldown(remaining),
+                });
+            }
+        }
+        for (bucket, cooldown) in buckets {
+            cooldowns.start(handler.name(), bucket, cooldown, now);
+        }
+        Ok(())
+    }
+}
+
+/// Fails unless the user has the permissions and [`Access`] `handler` asks
+/// for. Applies to the command and to its components and modals alike, so a
+/// message that was shared or kept open can't be used to bypass them.
+async fn authorize(
+    ctx: &Context,
+    handler: &dyn SlashCommand,
+    guild_id: Option<GuildId>,
+    user: UserId,
+    member: Option<&Member>,
+) -> Result<(), BotError> {
+    require_permissions(member, handler.required_permissions())?;
+    access::check(ctx, handler.access(), guild_id, user, member).await
+}
+
+/// Fails unless `member` has every permission in `required`. Interactions
+/// outside of servers have no member.
+pub fn require_permissions(member: Option<&Member>, required: Permissions) -> Result<(), BotError> {
+    if required.is_empty() {
+        return Ok(());
+    }
+    let permissions = member.and_then(|member| member.permissions).ok_or(BotError::GuildOnly)?;
+    if permissions.contains(required) {
+        Ok(())
+    } else {
+        Err(BotError::MissingPermission(required))
+    }
+}
+
+/// The language replies in `guild_id` are written in.
+pub async fn guild_language(ctx: &Context, guild_id: Option<GuildId>) -> Language {
+    match guild_id {
+        Some(guild_id) => crate::db::store(ctx)
+            .await
+            .get_guild_language(guild_id.get())
+            .await
+            .unwrap_or(Language::English),
+        None => Language::English,
+    }
+}
+
+fn error_message(message: String) -> CreateInteractionResponseMessage {
+    CreateInteractionResponseMessage::new()
+        .content(message)
+        .ephemeral(true)
+        .allowed_mentions(CreateAllowedMentions::new())
+}
+
+async fn respond(ctx: &Context, command: &CommandInteraction, deferred: bool, ephemeral: bool, reply: Reply) {
+    let Reply::Message { content, attachments } = reply else {
+        return;
+    };
+
+    // Replies may list users and roles, never ping them
+    let result = if deferred {
+        let builder = attachments.into_iter().fold(
+            EditInteractionResponse::new()
+                .content(content)
+                .allowed_mentions(CreateAllowedMentions::new()),
+            |builder, attachment| builder.new_attachment(attachment),
+        );
+        command.edit_response(&ctx.http, builder).await.map(|_| ())
+    } else {
+        let data = CreateInteractionResponseMessage::new()
+            .content(content)
+            .add_files(attachments)
+            .ephemeral(ephemeral)
+            .allowed_mentions(CreateAllowedMentions::new());
+        command.create_response(&ctx.http, CreateInteractionResponse::Message(data)).await
+    };
+    if let Err(why) = result {
+        error!("Cannot respond to slash command: {why}");
+    }
+}
+
+/// Tells only the invoking user what went wrong.
+async fn respond_error(ctx: &Context, command: &CommandInteraction, deferred: bool, message: String) {
+    let result = if deferred {
+        // The deferred response may be public, so replace it with an
+        // ephemeral followup
+        if let Err(why) = command.delete_response(&ctx.http).await {
+            error!("Cannot delete deferred response: {why}");
+        }
+        let followup = CreateInteractionResponseFollowup::new()
+            .content(message)
+            .ephemeral(true)
+            .allowed_mentions(CreateAllowedMentions::new());
+        command.create_followup(&ctx.http, followup).await.map(|_| ())
+    } else {
+        command.create_response(&ctx.http, CreateInteractionResponse::Message(error_message(message))).await
+    };
+    if let Err(why) = result {
+        error!("Cannot send error response: {why}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn cooldown_is_per_bucket_and_command() {
+        let now = Instant::now();
+        let cooldown = Duration::from_secs(10);
+        let user = Bucket::User(UserId::new(1));
+        let mut cooldowns = Cooldowns::default();
+
+        assert_eq!(cooldowns.remaining("tts", user, now), None);
+        cooldowns.start("tts", user, cooldown, now);
+        assert_eq!(cooldowns.remaining("tts", user, now + Duration::from_secs(4)), Some(Duration::from_secs(6)));
+        assert_eq!(cooldowns.remaining("tts", Bucket::User(UserId::new(2)), now), None);
+        assert_eq!(cooldowns.remaining("tts", Bucket::Guild(GuildId::new(1)), now), None);
+        assert_eq!(cooldowns.remaining("dict", user, now), None);
+        assert_eq!(cooldowns.remaining("tts", user, now + cooldown), None);
+    }
+
+    #[test]
+    fn shorter_cooldowns_dont_forget_longer_ones() {
+        let now = Instant::now();
+        let guild = Bucket::Guild(GuildId::new(1));
+        let mut cooldowns = Cooldowns::default();
+
+        cooldowns.start("connect", guild, Duration::from_secs(60), now);
+        cooldowns.start("tts", Bucket::User(UserId::new(1)), Duration::from_secs(1), now + Duration::from_secs(5));
+        assert_eq!(cooldowns.remaining("connect", guild, now + Duration::from_secs(5)), Some(Duration::from_secs(55)));
+    }
+
+    #[test]
+    fn registered_names_match_and_are_unique() {
+        let mut names = HashSet::new();
+        for command in &super::super::REGISTRY.commands {
+            let builder = serde_json::to_value(command.register()).unwrap();
+            assert_eq!(builder["name"], command.name());
+            assert!(names.insert(command.name()), "/{} is registered twice", command.name());
+        }
+    }
+}
@@ -0,0 +1,622 @@
+//! `/settings`: a panel for the server's reading settings.
+//!
+//! Select menus apply a setting as soon as it's picked, and settings that
+//! take free text open a modal. Every custom ID carries the panel's version
+//! and the user who opened it, so a panel from an older release or someone
+//! else's panel is refused instead of misread. The settings are loaded again
+//! on every interaction, so an old panel never writes back stale values.
+//! The panel is for TTS managers, like the commands it stands in for.
+
+use serenity::async_trait;
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateCommand, CreateEmbed, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateModal, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption,
+};
+use serenity::model::application::{
+    ActionRowComponent, ButtonStyle, CommandInteraction, ComponentInteraction, ComponentInteractionDataKind,
+    InputTextStyle, ModalInteraction,
+};
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::*;
+
+use crate::commands::{callme, readsettings, Access, BotError, Reply, SlashCommand};
+use crate::config::VoiceConfig;
+use crate::db::language::Language;
+use crate::db::name_announce::NameAnnounce;
+use crate::db::{ReadSettings, SpeechSettings};
+use crate::tts::{skip, voices};
+
+/// Bumped whenever the custom ID layout or the meaning of a control changes,
+/// so panels opened before a release are refused.
+const VERSION: u32 = 1;
+
+/// The custom ID of the text field in every modal.
+const INPUT: &str = "value";
+
+/// The longest skip prefix list the modal accepts, one prefix per line.
+const MAX_PREFIXES_LENGTH: u16 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    /// The select menu that switches between sections.
+    Section,
+    Language,
+    Voice,
+    NameAnnounce,
+    Reading,
+    Filters,
+    SkipPrefixes,
+    MaxChars,
+}
+
+impl Control {
+    const ALL: [Control; 8] = [
+        Control::Section,
+        Control::Language,
+        Control::Voice,
+        Control::NameAnnounce,
+        Control::Reading,
+        Control::Filters,
+        Control::SkipPrefixes,
+        Control::MaxChars,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Control::Section => "section",
+            Control::Language => "language",
+            Control::Voice => "voice",
+            Control::NameAnnounce => "names",
+            Control::Reading => "reading",
+            Control::Filters => "filters",
+            Control::SkipPrefixes => "skip",
+            Control::MaxChars => "maxchars",
+        }
+    }
+
+    /// The section the control is shown in.
+    fn section(self) -> Section {
+        match self {
+            Control::Section | Control::Language => Section::Language,
+            Control::Voice => Section::Voice,
+            Control::NameAnnounce | Control::Reading => Section::Names,
+            Control::Filters | Control::SkipPrefixes => Section::Filters,
+            Control::MaxChars => Section::Limits,
+        }
+    }
+}
+
+/// A component's custom ID: `settings:<version>:<user id>:<control>`.
+#[derive(Debug, PartialEq, Eq)]
+struct CustomId {
+    user: UserId,
+    control: Control,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    /// Made by another version of the panel.
+    Outdated,
+    Malformed,
+}
+
+impl CustomId {
+    fn encode(&self) -> String {
+        format!("settings:{}:{}:{}", VERSION, self.user, self.control.as_str())
+    }
+
+    fn parse(custom_id: &str) -> Result<Self, ParseError> {
+        let mut parts = custom_id.split(':');
+        if parts.next() != Some("settings") {
+            return Err(ParseError::Malformed);
+        }
+        let version = parts.next().and_then(|version| version.parse::<u32>().ok()).ok_or(ParseError::Malformed)?;
+        if version != VERSION {
+            return Err(ParseError::Outdated);
+        }
+        let user = parts
+            .next()
+            .and_then(|user| user.parse::<u64>().ok())
+            .filter(|user| *user != 0)
+            .map(UserId::new)
+            .ok_or(ParseError::Malformed)?;
+        let control = parts
+            .next()
+            .and_then(|name| Control::ALL.into_iter().find(|control| control.as_str() == name))
+            .ok_or(ParseError::Malformed)?;
+        if parts.next().is_some() {
+            return Err(ParseError::Malformed);
+        }
+        Ok(CustomId { user, control })
+    }
+}
+
+/// The control `custom_id` refers to, if it belongs to `user`'s panel.
+fn authorize(custom_id: &str, user: UserId) -> Result<Control, BotError> {
+    match CustomId::parse(custom_id) {
+        Ok(id) if id.user == user => Ok(id.control),
+        Ok(_) => Err(BotError::invalid(
+            "This panel belongs to someone else, open your own with /settings",
+            "ほかの人のパネルです。/settings で自分のパネルを開いてください",
+        )),
+        Err(ParseError::Outdated) => Err(BotError::invalid(
+            "This panel is outdated, please open a new one with /settings",
+            "このパネルは古くなっています。/settings でもう一度開いてください",
+        )),
+        Err(ParseError::Malformed) => Err(BotError::Internal(format!("malformed settings custom ID {:?}", custom_id))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Language,
+    Voice,
+    Names,
+    Filters,
+    Limits,
+}
+
+impl Section {
+    const ALL: [Section; 5] = [Section::Language, Section::Voice, Section::Names, Section::Filters, Section::Limits];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Section::Language => "language",
+            Section::Voice => "voice",
+            Section::Names => "names",
+            Section::Filters => "filters",
+            Section::Limits => "limits",
+        }
+    }
+
+    fn label(self, language: Language) -> &'static str {
+        match (self, language) {
+            (Section::Language, Language::English) => "Language",
+            (Section::Language, Language::Japanese) => "言語",
+            (Section::Voice, Language::English) => "Voice",
+            (Section::Voice, Language::Japanese) => "声",
+            (Section::Names, Language::English) => "Names",
+            (Section::Names, Language::Japanese) => "名前の読み上げ",
+            (Section::Filters, Language::English) => "Filters",
+            (Section::Filters, Language::Japanese) => "読み上げる内容",
+            (Section::Limits, Language::English) => "Limits",
+            (Section::Limits, Language::Japanese) => "制限",
+        }
+    }
+}
+
+fn name_announce_label(mode: NameAnnounce, language: Language) -> &'static str {
+    match (mode, language) {
+        (NameAnnounce::Always, Language::English) => "Always",
+        (NameAnnounce::OnChange, Language::English) => "When the speaker changes",
+        (NameAnnounce::Never, Language::English) => "Never",
+        (NameAnnounce::Always, Language::Japanese) => "毎回",
+        (NameAnnounce::OnChange, Language::Japanese) => "話者が変わったとき",
+        (NameAnnounce::Never, Language::Japanese) => "読み上げない",
+    }
+}
+
+/// The settings the panel shows, as currently stored.
+struct Current {
+    language: Language,
+    voice: VoiceConfig,
+    speech: SpeechSettings,
+    name_announce: NameAnnounce,
+    /// The reading of the user who opened the panel.
+    reading: Option<String>,
+    read: ReadSettings,
+    skip_prefixes: Vec<String>,
+}
+
+impl Current {
+    async fn load(ctx: &Context, guild_id: GuildId, user: UserId) -> Result<Self, BotError> {
+        let store = crate::db::store(ctx).await;
+        let speech = store.get_speech_settings(guild_id.get()).await?;
+        Ok(Current {
+            language: store.get_guild_language(guild_id.get()).await?,
+            voice: voices::find(speech.voice.as_deref()),
+            speech,
+            name_announce: store.get_name_announce(guild_id.get()).await?,
+            reading: store.get_spoken_name(guild_id.get(), user.get()).await?,
+            read: store.get_read_settings(guild_id.get()).await?,
+            skip_prefixes: skip::prefixes(ctx, guild_id).await,
+        })
+    }
+}
+
+fn summary(current: &Current) -> CreateEmbed {
+    let language = current.language;
+    let or_dash = |text: String| if text.is_empty() { "-".to_string() } else { text };
+    let enabled = readsettings::labels(language)
+        .iter()
+        .zip(readsettings::values(&current.read))
+        .filter(|(_, enabled)| *enabled)
+        .map(|(label, _)| *label)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let prefixes = current.skip_prefixes.iter().map(|p| format!("`{}`", p)).collect::<Vec<_>>().join(" ");
+    let reading = current.reading.clone().unwrap_or_default();
+
+    let (title, fields) = match language {
+        Language::English => (
+            "Settings",
+            [
+                ("Language", "English".to_string()),
+                ("Voice", current.voice.name.clone()),
+                ("Read author names", name_announce_label(current.name_announce, language).to_string()),
+                ("Your reading", or_dash(reading)),
+                ("Also read", or_dash(enabled)),
+                ("Skip prefixes", or_dash(prefixes)),
+                ("Max length", format!("{} characters", current.speech.max_chars)),
+            ],
+        ),
+        Language::Japanese => (
+            "設定",
+            [
+                ("言語", "日本語".to_string()),
+                ("声", current.voice.name.clone()),
+                ("名前の読み上げ", name_announce_label(current.name_announce, language).to_string()),
+                ("あなたの読み", or_dash(reading)),
+                ("あわせて読み上げる内容", or_dash(enabled)),
+                ("スキップする接頭辞", or_dash(prefixes)),
+                ("最大文字数", format!("{} 文字", current.speech.max_chars)),
+            ],
+        ),
+    };
+    CreateEmbed::new().title(title).fields(fields.into_iter().map(|(name, value)| (name, value, true)))
+}
+
+fn select(custom_id: String, options: Vec<CreateSelectMenuOption>) -> CreateSelectMenu {
+    CreateSelectMenu::new(custom_id, CreateSelectMenuKind::String { options })
+}
+
+fn button(custom_id: String, label: &str) -> CreateButton {
+    CreateButton::new(custom_id).label(label).style(ButtonStyle::Secondary)
+}
+
+/// The panel showing `section`'s controls.
+fn panel(current: &Current, user: UserId, section: Section) -> CreateInteractionResponseMessage {
+    let id = |control| CustomId { user, control }.encode();
+    let language = current.language;
+
+    let sections = Section::ALL
+        .into_iter()
+        .map(|s| CreateSelectMenuOption::new(s.label(language), s.as_str()).default_selection(s == section))
+        .collect();
+    let mut rows = vec![CreateActionRow::SelectMenu(select(id(Control::Section), sections))];
+
+    match section {
+        Section::Language => {
+            let japanese = matches!(language, Language::Japanese);
+            let options = vec![
+                CreateSelectMenuOption::new("English", "english").default_selection(!japanese),
+                CreateSelectMenuOption::new("日本語", "japanese").default_selection(japanese),
+            ];
+            rows.push(CreateActionRow::SelectMenu(select(id(Control::Language), options)));
+        }
+        Section::Voice => {
+            // Select menus hold at most 25 options
+            let options = voices::all()
+                .into_iter()
+                .take(25)
+                .map(|voice| {
+                    let selected = voice.id == current.voice.id;
+                    CreateSelectMenuOption::new(voice.name, voice.id).default_selection(selected)
+                })
+                .collect();
+            rows.push(CreateActionRow::SelectMenu(select(id(Control::Voice), options)));
+        }
+        Section::Names => {
+            let options = [NameAnnounce::Always, NameAnnounce::OnChange, NameAnnounce::Never]
+                .into_iter()
+                .map(|mode| {
+                    CreateSelectMenuOption::new(name_announce_label(mode, language), mode.as_str())
+                        .default_selection(mode == current.name_announce)
+                })
+                .collect();
+            rows.push(CreateActionRow::SelectMenu(select(id(Control::NameAnnounce), options)));
+            let label = match language {
+                Language::English => "Set my reading",
+                Language::Japanese => "自分の読みを設定",
+            };
+            rows.push(CreateActionRow::Buttons(vec![button(id(Control::Reading), label)]));
+        }
+        Section::Filters => {
+            let options = readsettings::KEYS
+                .into_iter()
+                .zip(readsettings::labels(language))
+                .zip(readsettings::values(&current.read))
+                .map(|((key, label), enabled)| CreateSelectMenuOption::new(label, key).default_selection(enabled))
+                .collect();
+            let placeholder = match language {
+                Language::English => "Read only the message text",
+                Language::Japanese => "本文のみ読み上げる",
+            };
+            let filters = select(id(Control::Filters), options)
+                .placeholder(placeholder)
+                .min_values(0)
+                .max_values(readsettings::KEYS.len() as u8);
+            rows.push(CreateActionRow::SelectMenu(filters));
+            let label = match language {
+                Language::English => "Edit skip prefixes",
+                Language::Japanese => "スキップする接頭辞を編集",
+            };
+            rows.push(CreateActionRow::Buttons(vec![button(id(Control::SkipPrefixes), label)]));
+        }
+        Section::Limits => {
+            let label = match language {
+                Language::English => "Change max length",
+                Language::Japanese => "最大文字数を変更",
+            };
+            rows.push(CreateActionRow::Buttons(vec![button(id(Control::MaxChars), label)]));
+        }
+    }
+
+    CreateInteractionResponseMessage::new().embed(summary(current)).components(rows)
+}
+
+/// A modal with a single text field, prefilled with `value`.
+fn modal(custom_id: String, title: &str, style: InputTextStyle, label: &str, value: &str, max_length: u16) -> CreateModal {
+    let mut input = CreateInputText::new(style, label, INPUT).required(false).max_length(max_length);
+    if !value.is_empty() {
+        input = input.value(value);
+    }
+    CreateModal::new(custom_id, title).components(vec![CreateActionRow::InputText(input)])
+}
+
+/// The text entered in a modal's field.
+fn input(interaction: &ModalInteraction) -> String {
+    interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) if input.custom_id == INPUT => input.value.clone(),
+            _ => None,
+        })
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn parse_max_chars(value: &str) -> Result<u32, BotError> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|chars| (SpeechSettings::MIN_CHARS..=SpeechSettings::MAX_CHARS).contains(chars))
+        .ok_or_else(|| {
+            BotError::invalid(
+                format!(
+                    "Please enter a number from {} to {}",
+                    SpeechSettings::MIN_CHARS,
+                    SpeechSettings::MAX_CHARS
+                ),
+                format!(
+                    "{} から {} までの数字を入力してください",
+                    SpeechSettings::MIN_CHARS,
+                    SpeechSettings::MAX_CHARS
+                ),
+            )
+        })
+}
+
+/// One prefix per line, without blanks or duplicates.
+fn parse_prefixes(value: &str) -> Vec<String> {
+    let mut prefixes = Vec::new();
+    for prefix in value.lines().map(str::trim).filter(|prefix| !prefix.is_empty()) {
+        if !prefixes.iter().any(|p| p == prefix) {
+            prefixes.push(prefix.to_string());
+        }
+    }
+    prefixes
+}
+
+fn selected(kind: &ComponentInteractionDataKind) -> Result<&[String], BotError> {
+    match kind {
+        ComponentInteractionDataKind::StringSelect { values } => Ok(values),
+        _ => Err(BotError::Internal("expected a string select".to_string())),
+    }
+}
+
+async fn on_component(ctx: &Context, interaction: &ComponentInteraction) -> Result<(), BotError> {
+    let control = authorize(&interaction.data.custom_id, interaction.user.id)?;
+    let guild_id = interaction.guild_id.ok_or(BotError::GuildOnly)?;
+    let user = interaction.user.id;
+    let store = crate::db::store(ctx).await;
+    let first = |kind| selected(kind).map(|values| values.first().cloned().unwrap_or_default());
+
+    let section = match control {
+        Control::Section => {
+            let value = first(&interaction.data.kind)?;
+            Section::ALL
+                .into_iter()
+                .find(|section| section.as_str() == value)
+                .ok_or_else(|| BotError::Internal(format!("unknown settings section {:?}", value)))?
+        }
+        Control::Language => {
+            let language = Language::from(first(&interaction.data.kind)?);
+            store.set_guild_language(guild_id.get(), language).await?;
+            control.section()
+        }
+        Control::Voice => {
+            let voice = first(&interaction.data.kind)?;
+            if !voices::all().iter().any(|v| v.id == voice) {
+                return Err(BotError::invalid(
+                    "That voice is no longer available",
+                    "その声は現在利用できません",
+                ));
+            }
+            let mut speech = store.get_speech_settings(guild_id.get()).await?;
+            speech.voice = (voice != voices::DEFAULT).then_some(voice);
+            store.set_speech_settings(guild_id.get(), speech).await?;
+            control.section()
+        }
+        Control::NameAnnounce => {
+            let mode = NameAnnounce::from(first(&interaction.data.kind)?.as_str());
+            store.set_name_announce(guild_id.get(), mode).await?;
+            control.section()
+        }
+        Control::Filters => {
+            let values = selected(&interaction.data.kind)?;
+            let enabled = |key: &str| values.iter().any(|value| value == key);
+            let settings = ReadSettings {
+                replies: enabled("replies"),
+                attachments: enabled("attachments"),
+                stickers: enabled("stickers"),
+                embeds: enabled("embeds"),
+                polls: enabled("polls"),
+            };
+            store.set_read_settings(guild_id.get(), settings).await?;
+            control.section()
+        }
+        Control::Reading | Control::SkipPrefixes | Control::MaxChars => {
+            let current = Current::load(ctx, guild_id, user).await?;
+            let modal = open_modal(&current, user, control);
+            interaction.create_response(&ctx.http, CreateInteractionResponse::Modal(modal)).await?;
+            return Ok(());
+        }
+    };
+
+    let current = Current::load(ctx, guild_id, user).await?;
+    let response = CreateInteractionResponse::UpdateMessage(panel(&current, user, section));
+    interaction.create_response(&ctx.http, response).await?;
+    Ok(())
+}
+
+fn open_modal(current: &Current, user: UserId, control: Control) -> CreateModal {
+    let custom_id = CustomId { user, control }.encode();
+    let japanese = matches!(current.language, Language::Japanese);
+    match control {
+        Control::Reading => modal(
+            custom_id,
+            if japanese { "自分の読み" } else { "Your reading" },
+            InputTextStyle::Short,
+            if japanese { "名前の読み方 (空にするとリセット)" } else { "How your name is read (empty to reset)" },
+            current.reading.as_deref().unwrap_or_default(),
+            callme::MAX_READING_LENGTH as u16,
+        ),
+        Control::SkipPrefixes => modal(
+            custom_id,
+            if japanese { "スキップする接頭辞" } else { "Skip prefixes" },
+            InputTextStyle::Paragraph,
+            if japanese { "1 行に 1 つ" } else { "One per line" },
+            &current.skip_prefixes.join("\n"),
+            MAX_PREFIXES_LENGTH,
+        ),
+        _ => modal(
+            custom_id,
+            if japanese { "最大文字数" } else { "Max length" },
+            InputTextStyle::Short,
+            if japanese { "これより長いメッセージは省略します" } else { "Longer messages are cut off" },
+            &current.speech.max_chars.to_string(),
+            3,
+        ),
+    }
+}
+
+async fn on_modal(ctx: &Context, interaction: &ModalInteraction) -> Result<(), BotError> {
+    let control = authorize(&interaction.data.custom_id, interaction.user.id)?;
+    let guild_id = interaction.guild_id.ok_or(BotError::GuildOnly)?;
+    let user = interaction.user.id;
+    let store = crate::db::store(ctx).await;
+    let value = input(interaction);
+
+    match control {
+        Control::Reading if value.is_empty() => store.clear_spoken_name(guild_id.get(), user.get()).await?,
+        Control::Reading => {
+            callme::check_reading(&value)?;
+            store.set_spoken_name(guild_id.get(), user.get(), &value).await?;
+        }
+        Control::SkipPrefixes => store.set_skip_prefixes(guild_id.get(), &parse_prefixes(&value)).await?,
+        Control::MaxChars => {
+            let mut speech = store.get_speech_settings(guild_id.get()).await?;
+            speech.max_chars = parse_max_chars(&value)?;
+            store.set_speech_settings(guild_id.get(), speech).await?;
+        }
+        _ => return Err(BotError::Internal(format!("settings control {:?} has no modal", control))),
+    }
+
+    let current = Current::load(ctx, guild_id, user).await?;
+    let response = CreateInteractionResponse::UpdateMessage(panel(&current, user, control.section()));
+    interaction.create_response(&ctx.http, response).await?;
+    Ok(())
+}
+
+pub struct Settings;
+
+#[async_trait]
+impl SlashCommand for Settings {
+    fn name(&self) -> &'static str {
+        "settings"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Open a panel with the server's reading settings")
+            .description_localized("ja", "読み上げ設定のパネルを開きます")
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+        let current = Current::load(ctx, guild_id, command.user.id).await?;
+        let message = panel(&current, command.user.id, Section::Language).ephemeral(true);
+        command.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await?;
+        Ok(Reply::Responded)
+    }
+
+    /// Every control but "Your reading" changes the server's settings. The
+    /// registry checks this for the panel's components and modals too
+    fn access(&self) -> Access {
+        Access::Manager
+    }
+
+    /// Only the user who opened the panel can see and use it
+    fn ephemeral(&self) -> bool {
+        true
+    }
+
+    async fn component(&self, ctx: &Context, interaction: &ComponentInteraction) -> Result<(), BotError> {
+        on_component(ctx, interaction).await
+    }
+
+    async fn modal(&self, ctx: &Context, interaction: &ModalInteraction) -> Result<(), BotError> {
+        on_modal(ctx, interaction).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_ids_round_trip() {
+        for control in Control::ALL {
+            let id = CustomId { user: UserId::new(42), control };
+            let encoded = id.encode();
+            assert!(encoded.len() <= 100, "{} is too long for Discord", encoded);
+            assert_eq!(CustomId::parse(&encoded), Ok(id));
+        }
+    }
+
+    #[test]
+    fn stale_and_foreign_custom_ids_are_refused() {
+        assert_eq!(CustomId::parse("settings:0:42:voice"), Err(ParseError::Outdated));
+        assert_eq!(CustomId::parse("settings:1:42:volume"), Err(ParseError::Malformed));
+        assert_eq!(CustomId::parse("settings:1:0:voice"), Err(ParseError::Malformed));
+        assert_eq!(CustomId::parse("dict:1:42:voice"), Err(ParseError::Malformed));
+
+        let id = CustomId { user: UserId::new(42), control: Control::Voice }.encode();
+        assert_eq!(authorize(&id, UserId::new(42)).unwrap(), Control::Voice);
+        assert!(matches!(authorize(&id, UserId::new(7)), Err(BotError::InvalidInput { .. })));
+    }
+
+    #[test]
+    fn modal_input_is_validated() {
+        assert_eq!(parse_max_chars("200").unwrap(), 200);
+        assert!(parse_max_chars("5").is_err());
+        assert!(parse_max_chars("many").is_err());
+        assert_eq!(parse_prefixes(";\n\n  // \n;"), vec![";".to_string(), "//".to_string()]);
+    }
+}
@@ -0,0 +1,104 @@
+use serenity::builder::CreateCommand;
+use serenity::gateway::ShardManager;
+use serenity::model::application::CommandInteraction;
+use serenity::prelude::*;
+use serenity::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::commands::registry::guild_language;
+use crate::commands::{Access, BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+
+/// The client's shard manager, so commands can report on every shard.
+pub struct ShardManagerKey;
+
+impl TypeMapKey for ShardManagerKey {
+    type Value = Arc<ShardManager>;
+}
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let shard_manager = ctx
+        .data
+        .read()
+        .await
+        .get::<ShardManagerKey>()
+        .cloned()
+        .ok_or_else(|| BotError::Internal("shard manager is not in the client data".to_string()))?;
+
+    let total = ctx.cache.shard_count();
+    let mut guilds = HashMap::new();
+    for guild_id in ctx.cache.guilds() {
+        *guilds.entry(serenity::utils::shard_id(guild_id, total)).or_insert(0) += 1;
+    }
+
+    // Collect the calls first so the manager's map isn't held across awaits
+    let mut calls = HashMap::new();
+    if let Some(manager) = songbird::get(ctx).await {
+        let handlers = manager.iter().collect::<Vec<_>>();
+        for (guild_id, handler_lock) in handlers {
+            if handler_lock.lock().await.current_channel().is_some() {
+                let guild_id = serenity::model::id::GuildId::from(guild_id.0);
+                *calls.entry(serenity::utils::shard_id(guild_id, total)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let language = guild_language(ctx, command.guild_id).await;
+    let this_shard = command.guild_id.map(|guild_id| serenity::utils::shard_id(guild_id, total));
+
+    let runners = shard_manager.runners.lock().await;
+    let mut shard_ids = runners.keys().copied().collect::<Vec<_>>();
+    shard_ids.sort_by_key(|id| id.0);
+
+    let mut lines = vec![match language {
+        Language::English => format!("**Shards** ({} running in this process, {} total)", runners.len(), total),
+        Language::Japanese => format!("**シャード** (このプロセスで {} 個稼働中、合計 {} 個)", runners.len(), total),
+    }];
+    for shard_id in shard_ids {
+        let runner = &runners[&shard_id];
+        let latency = runner
+            .latency
+            .map(|latency| format!("{} ms", latency.as_millis()))
+            .unwrap_or_else(|| "-".to_string());
+        let guild_count = guilds.get(&shard_id.0).copied().unwrap_or(0);
+        let call_count = calls.get(&shard_id.0).copied().unwrap_or(0);
+        let marker = if this_shard == Some(shard_id.0) { " ⬅" } else { "" };
+        lines.push(match language {
+            Language::English => format!(
+                "`#{}` {} · {} · {} guilds · {} calls{}",
+                shard_id.0, runner.stage, latency, guild_count, call_count, marker
+            ),
+            Language::Japanese => format!(
+                "`#{}` {} · {} · サーバー {} · 通話 {}{}",
+                shard_id.0, runner.stage, latency, guild_count, call_count, marker
+            ),
+        });
+    }
+
+    Ok(lines.join("\n"))
+}
+
+pub struct Shards;
+
+#[async_trait]
+impl SlashCommand for Shards {
+    fn name(&self) -> &'static str {
+        "shards"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Show the status and latency of the bot's shards")
+            .description_localized("ja", "シャードの状態と遅延を表示します")
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+
+    /// Shows details of the whole deployment, not just this server
+    fn access(&self) -> Access {
+        Access::Owner
+    }
+}
@@ -0,0 +1,291 @@
+//! `/status`: how the bot is doing, from this shard's and this server's point
+//! of view.
+//!
+//! Every latency is measured when the command runs, except the TTS backend's,
+//! which is the last synthesis request: probing the backend from a command
+//! would spend its quota and could trip the circuit breaker.
+
+use lazy_static::lazy_static;
+use serenity::builder::CreateCommand;
+use serenity::model::application::CommandInteraction;
+use serenity::model::id::GuildId;
+use serenity::prelude::*;
+use serenity::async_trait;
+use std::time::{Duration, Instant};
+
+use crate::commands::registry::guild_language;
+use crate::commands::shards::ShardManagerKey;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+use crate::metrics;
+use crate::tts::{self, circuit::CircuitState};
+
+/// The commit the bot was built from, set by `build.rs`.
+const GIT_HASH: Option<&str> = option_env!("GIT_HASH");
+
+lazy_static! {
+    /// When the process started, for the uptime.
+    static ref STARTED: Instant = Instant::now();
+}
+
+/// Starts the uptime clock. Called first thing in `main`.
+pub fn start_clock() {
+    lazy_static::initialize(&STARTED);
+}
+
+/// The guild's call, as the database and songbird see it.
+#[derive(Debug, Default)]
+struct Voice {
+    /// The voice channel songbird is connected to.
+    channel_id: Option<u64>,
+    /// Unix timestamp in seconds the session started at.
+    started_at: Option<i64>,
+    text_channels: usize,
+    playing: bool,
+    queued: usize,
+}
+
+/// Everything `/status` shows. Measurements that failed are `None`.
+#[derive(Debug)]
+struct Report {
+    shard_id: u32,
+    heartbeat: Option<Duration>,
+    rest: Option<Duration>,
+    backend: CircuitState,
+    backend_latency: Option<Duration>,
+    database: Option<Duration>,
+    /// Cached utterances and capacity.
+    cache_usage: (usize, usize),
+    cache_hits: u64,
+    cache_misses: u64,
+    guilds: usize,
+    users: usize,
+    uptime: Duration,
+    voice: Option<Voice>,
+}
+
+async fn measure(ctx: &Context, guild_id: Option<GuildId>) -> Report {
+    let heartbeat = match ctx.data.read().await.get::<ShardManagerKey>().cloned() {
+        Some(shard_manager) => {
+            shard_manager.runners.lock().await.get(&ctx.shard_id).and_then(|runner| runner.latency)
+        }
+        None => None,
+    };
+
+    let started = Instant::now();
+    let rest = ctx.http.get_current_user().await.ok().map(|_| started.elapsed());
+
+    let store = crate::db::store(ctx).await;
+    let started = Instant::now();
+    let database = store.ping().await.ok().map(|_| started.elapsed());
+
+    let voice = match guild_id {
+        Some(guild_id) => {
+            let mut voice = Voice::default();
+            if let Ok(Some(session)) = store.get_session(guild_id.get()).await {
+                voice.started_at = Some(session.started_at);
+                voice.text_channels = session.text_channel_ids.len();
+            }
+            if let Some(manager) = songbird::get(ctx).await {
+                if let Some(handler_lock) = manager.get(guild_id) {
+                    let handler = handler_lock.lock().await;
+                    voice.channel_id = handler.current_channel().map(|channel| channel.0.get());
+                    voice.playing = handler.queue().current().is_some();
+                    voice.queued = handler.queue().len();
+                }
+            }
+            Some(voice)
+        }
+        None => None,
+    };
+
+    Report {
+        shard_id: ctx.shard_id.0,
+        heartbeat,
+        rest,
+        backend: tts::backend_state(),
+        backend_latency: tts::last_backend_latency(),
+        database,
+        cache_usage: tts::cache_usage(),
+        cache_hits: metrics::CACHE_HITS.get(),
+        cache_misses: metrics::CACHE_MISSES.get(),
+        guilds: ctx.cache.guild_count(),
+        users: ctx.cache.user_count(),
+        uptime: STARTED.elapsed(),
+        voice,
+    }
+}
+
+/// E.g. `2d 3h 4m`, or `12s` right after startup.
+fn format_uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3_600 % 24, seconds / 60 % 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, _) => format!("{}m", minutes),
+        (0, _, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// Milliseconds, with a decimal below 10 ms where it matters.
+fn format_latency(latency: Option<Duration>, language: Language) -> String {
+    match (latency, language) {
+        (Some(latency), _) if latency < Duration::from_millis(10) => {
+            format!("{:.1} ms", latency.as_secs_f64() * 1000.0)
+        }
+        (Some(latency), _) => format!("{} ms", latency.as_millis()),
+        (None, Language::English) => "unavailable".to_string(),
+        (None, Language::Japanese) => "取得できません".to_string(),
+    }
+}
+
+fn render(report: &Report, language: Language) -> String {
+    let latency = |latency| format_latency(latency, language);
+    let commit = GIT_HASH.filter(|hash| !hash.is_empty()).unwrap_or("unknown");
+    let version = format!("v{} ({})", env!("CARGO_PKG_VERSION"), commit);
+    let uptime = format_uptime(report.uptime);
+    let (cached, capacity) = report.cache_usage;
+    let lookups = report.cache_hits + report.cache_misses;
+    let hit_rate = (report.cache_hits * 100).checked_div(lookups).unwrap_or(0);
+    let backend_latency = match report.backend_latency {
+        Some(_) => latency(report.backend_latency),
+        None => "-".to_string(),
+    };
+
+    let mut lines = match language {
+        Language::English => vec![
+            format!("**Status** · {} · up {}", version, uptime),
+            format!("Gateway heartbeat: {} (shard #{})", latency(report.heartbeat), report.shard_id),
+            format!("REST round trip: {}", latency(report.rest)),
+            format!("TTS backend: {} · last request {}", report.backend.as_str(), backend_latency),
+            format!("Database: {}", latency(report.database)),
+            format!("Synthesis cache: {}/{} entries · {}% hits", cached, capacity, hit_rate),
+            format!("Discord cache: {} servers · {} users", report.guilds, report.users),
+        ],
+        Language::Japanese => vec![
+            format!("**ステータス** · {} · 稼働時間 {}", version, uptime),
+            format!("ゲートウェイ: {} (シャード #{})", latency(report.heartbeat), report.shard_id),
+            format!("REST: {}", latency(report.rest)),
+            format!("TTS バックエンド: {} · 前回のリクエスト {}", report.backend.as_str(), backend_latency),
+            format!("データベース: {}", latency(report.database)),
+            format!("合成キャッシュ: {}/{} 件 · ヒット率 {}%", cached, capacity, hit_rate),
+            format!("Discord キャッシュ: サーバー {} · ユーザー {}", report.guilds, report.users),
+        ],
+    };
+
+    if let Some(voice) = &report.voice {
+        let playback = match (voice.playing, language) {
+            (true, Language::English) => "playing",
+            (false, Language::English) => "idle",
+            (true, Language::Japanese) => "再生中",
+            (false, Language::Japanese) => "待機中",
+        };
+        let since = match (voice.started_at, language) {
+            (Some(started_at), Language::English) => format!(" since <t:{}:R>", started_at),
+            (Some(started_at), Language::Japanese) => format!(" (<t:{}:R> から)", started_at),
+            (None, _) => String::new(),
+        };
+        lines.push(match (voice.channel_id, language) {
+            (Some(channel_id), Language::English) => format!(
+                "Voice: <#{}>{} · {} read channels · {} · {} in queue",
+                channel_id, since, voice.text_channels, playback, voice.queued
+            ),
+            (Some(channel_id), Language::Japanese) => format!(
+                "ボイス: <#{}>{} · 読み上げチャンネル {} · {} · キュー {} 件",
+                channel_id, since, voice.text_channels, playback, voice.queued
+            ),
+            (None, Language::English) => "Voice: not connected in this server".to_string(),
+            (None, Language::Japanese) => "ボイス: このサーバーでは接続していません".to_string(),
+        });
+    }
+
+    lines.join("\n")
+}
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> String {
+    let language = guild_language(ctx, command.guild_id).await;
+    render(&measure(ctx, command.guild_id).await, language)
+}
+
+pub struct Status;
+
+#[async_trait]
+impl SlashCommand for Status {
+    fn name(&self) -> &'static str {
+        "status"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Show the bot's latency, TTS backend, database and voice session")
+            .description_localized("ja", "Bot の遅延、TTS バックエンド、データベース、ボイスの状態を表示します")
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        Ok(Reply::from(run(command, ctx).await))
+    }
+
+    fn cooldown(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+
+    /// A slow REST round trip shouldn't leave the interaction unanswered
+    fn defers(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_readable() {
+        assert_eq!(format_uptime(Duration::from_secs(12)), "12s");
+        assert_eq!(format_uptime(Duration::from_secs(5 * 60 + 3)), "5m");
+        assert_eq!(format_uptime(Duration::from_secs(3 * 3_600 + 4 * 60)), "3h 4m");
+        assert_eq!(format_uptime(Duration::from_secs(2 * 86_400 + 3 * 3_600 + 4 * 60)), "2d 3h 4m");
+
+        assert_eq!(format_latency(Some(Duration::from_micros(360)), Language::English), "0.4 ms");
+        assert_eq!(format_latency(Some(Duration::from_millis(120)), Language::English), "120 ms");
+        assert_eq!(format_latency(None, Language::English), "unavailable");
+    }
+
+    #[test]
+    fn report_shows_failures_and_the_call() {
+        let mut report = Report {
+            shard_id: 1,
+            heartbeat: None,
+            rest: Some(Duration::from_millis(80)),
+            backend: CircuitState::Open,
+            backend_latency: None,
+            database: Some(Duration::from_millis(2)),
+            cache_usage: (3, 100),
+            cache_hits: 1,
+            cache_misses: 3,
+            guilds: 2,
+            users: 40,
+            uptime: Duration::from_secs(90),
+            voice: None,
+        };
+        let rendered = render(&report, Language::English);
+        assert!(rendered.contains("Gateway heartbeat: unavailable (shard #1)"));
+        assert!(rendered.contains("TTS backend: open · last request -"));
+        assert!(rendered.contains("3/100 entries · 25% hits"));
+        assert!(!rendered.contains("Voice:"));
+
+        report.voice = Some(Voice::default());
+        assert!(render(&report, Language::English).contains("Voice: not connected"));
+        report.voice = Some(Voice {
+            channel_id: Some(7),
+            started_at: Some(1_700_000_000),
+            text_channels: 2,
+            playing: true,
+            queued: 3,
+        });
+        assert!(render(&report, Language::English)
+            .contains("Voice: <#7> since <t:1700000000:R> · 2 read channels · playing · 3 in queue"));
+        assert!(render(&report, Language::Japanese).contains("ボイス: <#7> (<t:1700000000:R> から) · 読み上げチャンネル 2"));
+    }
+}
@@ -0,0 +1,267 @@
+//! Keeps the commands registered with Discord in sync with the [`Registry`].
+//!
+//! Instead of overwriting every command on each start, the registered
+//! commands are fetched and compared with the registry, and only the ones
+//! that were added, changed or removed are sent. In development mode
+//! (`commands.dev_guilds`) the commands are registered in those guilds only,
+//! where updates show up immediately, and the global commands are left alone.
+
+use serde_json::Value;
+use serenity::builder::CreateCommand;
+use serenity::http::Http;
+use serenity::model::application::Command;
+use serenity::model::id::{CommandId, GuildId};
+use std::fmt;
+use tracing::{debug, error, info};
+
+use super::Registry;
+use crate::config::CommandsConfig;
+
+/// Where commands are registered.
+#[derive(Debug, Clone, Copy)]
+pub enum Scope {
+    Global,
+    Guild(GuildId),
+}
+
+impl Scope {
+    /// The scopes `config` registers commands in.
+    pub fn configured(config: &CommandsConfig) -> Vec<Scope> {
+        if config.dev_mode() {
+            config.dev_guilds.iter().map(|id| Scope::Guild(GuildId::new(*id))).collect()
+        } else {
+            vec![Scope::Global]
+        }
+    }
+
+    async fn commands(self, http: &Http) -> serenity::Result<Vec<Command>> {
+        match self {
+            Scope::Global => Command::get_global_commands_with_localizations(http).await,
+            Scope::Guild(guild_id) => guild_id.get_commands_with_localizations(http).await,
+        }
+    }
+
+    /// Creates `builder`, or replaces the command with the same name.
+    async fn upsert(self, http: &Http, builder: CreateCommand) -> serenity::Result<Command> {
+        match self {
+            Scope::Global => Command::create_global_command(http, builder).await,
+            Scope::Guild(guild_id) => guild_id.create_command(http, builder).await,
+        }
+    }
+
+    async fn delete(self, http: &Http, command_id: CommandId) -> serenity::Result<()> {
+        match self {
+            Scope::Global => Command::delete_global_command(http, command_id).await,
+            Scope::Guild(guild_id) => guild_id.delete_command(http, command_id).await,
+        }
+    }
+
+    async fn set(self, http: &Http, builders: Vec<CreateCommand>) -> serenity::Result<Vec<Command>> {
+        match self {
+            Scope::Global => Command::set_global_commands(http, builders).await,
+            Scope::Guild(guild_id) => guild_id.set_commands(http, builders).await,
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Global => write!(f, "global"),
+            Scope::Guild(guild_id) => write!(f, "guild {}", guild_id),
+        }
+    }
+}
+
+/// What has to change for the registered commands to match the registry.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// Indices into the desired commands.
+    pub create: Vec<usize>,
+    pub update: Vec<usize>,
+    pub delete: Vec<CommandId>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.update.is_empty() && self.delete.is_empty()
+    }
+}
+
+/// Compares the desired commands with the registered ones, both serialized
+/// to JSON.
+pub fn plan(desired: &[Value], existing: &[(CommandId, Value)]) -> Plan {
+    let mut plan = Plan::default();
+
+    for (index, command) in desired.iter().enumerate() {
+        let spec = comparable(command);
+        match existing.iter().find(|(_, registered)| registered["name"] == command["name"]) {
+            None => plan.create.push(index),
+            Some((_, registered)) if comparable(registered) != spec => plan.update.push(index),
+            Some(_) => {}
+        }
+    }
+    for (id, registered) in existing {
+        if !desired.iter().any(|command| command["name"] == registered["name"]) {
+            plan.delete.push(*id);
+        }
+    }
+
+    plan
+}
+
+/// The parts of a command both the builder and Discord's copy describe, with
+/// empty and default values dropped so the two compare equal when nothing
+/// changed.
+fn comparable(command: &Value) -> Value {
+    const KEYS: &[&str] = &[
+        "type",
+        "name",
+        "name_localizations",
+        "description",
+        "description_localizations",
+        "options",
+        "default_member_permissions",
+        "nsfw",
+    ];
+
+    let mut spec = serde_json::Map::new();
+    for key in KEYS {
+        if let Some(value) = normalize(&command[*key]) {
+            spec.insert(key.to_string(), value);
+        }
+    }
+    // Builders leave out the type of slash commands
+    spec.entry("type").or_insert(Value::from(1.0));
+    Value::Object(spec)
+}
+
+fn normalize(value: &Value) -> Option<Value> {
+    match value {
+        Value::Null | Value::Bool(false) => None,
+        Value::String(s) if s.is_empty() => None,
+        // Discord returns `5` for a minimum the builder sent as `5.0`
+        Value::Number(n) => n.as_f64().map(Value::from),
+        Value::Array(items) => {
+            let items = items.iter().filter_map(normalize).collect::<Vec<_>>();
+            (!items.is_empty()).then_some(Value::Array(items))
+        }
+        Value::Object(fields) => {
+            let fields = fields
+                .iter()
+                .filter_map(|(key, value)| normalize(value).map(|value| (key.clone(), value)))
+                .collect::<serde_json::Map<_, _>>();
+            (!fields.is_empty()).then_some(Value::Object(fields))
+        }
+        _ => Some(value.clone()),
+    }
+}
+
+/// Brings the commands in every configured scope in line with `registry`.
+pub async fn register(http: &Http, registry: &Registry, config: &CommandsConfig) {
+    for scope in Scope::configured(config) {
+        if let Err(e) = sync(http, registry, scope).await {
+            error!("Error registering {} commands: {}", scope, e);
+        }
+    }
+}
+
+async fn sync(http: &Http, registry: &Registry, scope: Scope) -> serenity::Result<()> {
+    let builders = registry.builders();
+    let desired = builders
+        .iter()
+        .map(|builder| serde_json::to_value(builder).unwrap_or_default())
+        .collect::<Vec<_>>();
+    let existing = scope
+        .commands(http)
+        .await?
+        .into_iter()
+        .map(|command| (command.id, serde_json::to_value(&command).unwrap_or_default()))
+        .collect::<Vec<_>>();
+
+    let plan = plan(&desired, &existing);
+    if plan.is_empty() {
+        info!("{} commands are up to date", scope);
+        return Ok(());
+    }
+
+    for &index in plan.create.iter().chain(&plan.update) {
+        debug!("Registering /{} ({})", desired[index]["name"].as_str().unwrap_or_default(), scope);
+        scope.upsert(http, builders[index].clone()).await?;
+    }
+    for &command_id in &plan.delete {
+        debug!("Deleting command {} ({})", command_id, scope);
+        scope.delete(http, command_id).await?;
+    }
+    info!(
+        "Synced {} commands: {} created, {} updated, {} deleted",
+        scope,
+        plan.create.len(),
+        plan.update.len(),
+        plan.delete.len()
+    );
+    Ok(())
+}
+
+/// Deletes every command the bot registered in the configured scopes. Used by
+/// `--purge-commands`.
+pub async fn purge(token: &str, config: &CommandsConfig) -> serenity::Result<()> {
+    let http = Http::new(token);
+    let application = http.get_current_application_info().await?;
+    http.set_application_id(application.id);
+
+    for scope in Scope::configured(config) {
+        scope.set(&http, Vec::new()).await?;
+        info!("Deleted all {} commands", scope);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use serenity::builder::CreateCommandOption;
+    use serenity::model::application::CommandOptionType;
+
+    fn builder(description: &str) -> Value {
+        let command = CreateCommand::new("dict").description(description).add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "limit", "How many")
+                .min_int_value(5)
+                .required(true),
+        );
+        serde_json::to_value(command).unwrap()
+    }
+
+    /// What Discord sends back for `builder("Edit the dictionary")`, as
+    /// serenity serializes it again.
+    fn registered() -> Value {
+        let command: Command = serde_json::from_value(json!({
+            "id": "1", "application_id": "2", "version": "3", "type": 1,
+            "name": "dict", "name_localizations": null,
+            "description": "Edit the dictionary", "description_localizations": null,
+            "options": [{ "type": 4, "name": "limit", "description": "How many", "required": true, "min_value": 5 }],
+            "default_member_permissions": null, "dm_permission": true, "nsfw": false,
+        }))
+        .unwrap();
+        serde_json::to_value(command).unwrap()
+    }
+
+    #[test]
+    fn unchanged_commands_are_left_alone() {
+        let existing = [(CommandId::new(1), registered())];
+        assert!(plan(&[builder("Edit the dictionary")], &existing).is_empty());
+    }
+
+    #[test]
+    fn changes_are_diffed_by_name() {
+        let mut other = registered();
+        other["name"] = json!("modal");
+        let existing = [(CommandId::new(1), registered()), (CommandId::new(7), other)];
+        let ping = serde_json::to_value(CreateCommand::new("ping").description("Ping")).unwrap();
+
+        let plan = plan(&[builder("Add or remove readings"), ping], &existing);
+
+        assert_eq!(plan, Plan { create: vec![1], update: vec![0], delete: vec![CommandId::new(7)] });
+    }
+}
@@ -0,0 +1,311 @@
+use serenity::builder::{AutocompleteChoice, CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue};
+use serenity::model::permissions::Permissions;
+use serenity::prelude::*;
+use serenity::async_trait;
+use crate::commands::autocomplete::{self, Candidate};
+use crate::commands::access;
+use crate::commands::registry::require_permissions;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+use crate::db::{IgnoreKind, Store};
+use crate::tts::skip;
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let store = crate::db::store(ctx).await;
+    let store = store.as_ref();
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+
+    let options = command.data.options();
+    let Some(subcommand) = options.first() else {
+        return Err(BotError::invalid("Please choose a subcommand", "サブコマンドを選んでください"));
+    };
+
+    match (subcommand.name, &subcommand.value) {
+        ("optout", ResolvedValue::SubCommand(_)) => optout(command, store, language).await,
+        ("ignore", ResolvedValue::SubCommandGroup(args)) => ignore(command, ctx, store, language, args).await,
+        ("ignored", ResolvedValue::SubCommand(_)) => ignored(command, store, language).await,
+        ("skip", ResolvedValue::SubCommandGroup(args)) => skip(command, ctx, store, language, args).await,
+        ("manager", ResolvedValue::SubCommand(args)) => manager(command, store, language, args).await,
+        _ => Err(BotError::Internal(format!("unknown subcommand /tts {}", subcommand.name))),
+    }
+}
+
+async fn optout(command: &CommandInteraction, store: &dyn Store, language: Language) -> Result<String, BotError> {
+    let user_id = command.user.id.get();
+    let opted_out = store.is_opted_out(user_id).await?;
+
+    store.set_opted_out(user_id, !opted_out).await?;
+
+    Ok(match (language, !opted_out) {
+        (Language::English, true) => "Your messages will no longer be read aloud".to_string(),
+        (Language::English, false) => "Your messages will be read aloud again".to_string(),
+        (Language::Japanese, true) => "あなたのメッセージは読み上げられなくなりました".to_string(),
+        (Language::Japanese, false) => "あなたのメッセージを再び読み上げます".to_string(),
+    })
+}
+
+async fn ignore(
+    command: &CommandInteraction,
+    ctx: &Context,
+    store: &dyn Store,
+    language: Language,
+    args: &[ResolvedOption<'_>],
+) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+
+    require_manager(command, ctx).await?;
+
+    let Some(ResolvedOption { name, value: ResolvedValue::SubCommand(target), .. }) = args.first() else {
+        return Err(BotError::invalid("Please choose user, role or channel", "user・role・channel のいずれかを選んでください"));
+    };
+    let (kind, target_id) = match (*name, target.first().map(|option| &option.value)) {
+        ("user", Some(ResolvedValue::User(user, _))) => (IgnoreKind::User, user.id.get()),
+        ("role", Some(ResolvedValue::Role(role))) => (IgnoreKind::Role, role.id.get()),
+        ("channel", Some(ResolvedValue::Channel(channel))) => (IgnoreKind::Channel, channel.id.get()),
+        _ => return Err(BotError::invalid("Please provide a valid target", "対象を正しく指定してください")),
+    };
+
+    let ignored = store.toggle_ignore(guild_id.get(), kind, target_id).await?;
+
+    let target = mention(kind, target_id);
+    Ok(match (language, ignored) {
+        (Language::English, true) => format!("{} will no longer be read aloud", target),
+        (Language::English, false) => format!("{} will be read aloud again", target),
+        (Language::Japanese, true) => format!("{} を読み上げ対象から除外しました", target),
+        (Language::Japanese, false) => format!("{} を再び読み上げます", target),
+    })
+}
+
+async fn ignored(command: &CommandInteraction, store: &dyn Store, language: Language) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let entries = store.get_ignores(guild_id.get()).await?;
+
+    if entries.is_empty() {
+        return Ok(match language {
+            Language::English => "Nothing is ignored".to_string(),
+            Language::Japanese => "除外されている対象はありません".to_string(),
+        });
+    }
+
+    Ok(entries
+        .into_iter()
+        .map(|(kind, target_id)| format!("{} {}", kind.as_str(), mention(kind, target_id)))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+async fn skip(
+    command: &CommandInteraction,
+    ctx: &Context,
+    store: &dyn Store,
+    language: Language,
+    args: &[ResolvedOption<'_>],
+) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let Some(ResolvedOption { name, value: ResolvedValue::SubCommand(args), .. }) = args.first() else {
+        return Err(BotError::invalid("Please choose add, remove or list", "add・remove・list のいずれかを選んでください"));
+    };
+    let prefix = args.iter().find_map(|option| match option.value {
+        ResolvedValue::String(prefix) if option.name == "prefix" => Some(prefix.trim().to_string()),
+        _ => None,
+    });
+
+    let mut prefixes = skip::prefixes(ctx, guild_id).await;
+    match (*name, prefix) {
+        ("add", Some(prefix)) if !prefix.is_empty() => {
+            require_manager(command, ctx).await?;
+            if !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
+        }
+        ("remove", Some(prefix)) => {
+            require_manager(command, ctx).await?;
+            if !prefixes.contains(&prefix) {
+                return Err(BotError::invalid(
+                    format!("`{}` is not a skip prefix", prefix),
+                    format!("`{}` はスキップする接頭辞ではありません", prefix),
+                ));
+            }
+            prefixes.retain(|p| *p != prefix);
+        }
+        ("list", _) => {
+            let listed = if prefixes.is_empty() {
+                "-".to_string()
+            } else {
+                prefixes.iter().map(|p| format!("`{}`", p)).collect::<Vec<_>>().join(" ")
+            };
+            return Ok(match language {
+                Language::English => format!(
+                    "Skip prefixes: {}\nText between `{}` markers is not read either.",
+                    listed,
+                    skip::INLINE_MARKER,
+                ),
+                Language::Japanese => format!(
+                    "スキップする接頭辞: {}\n`{}` で囲んだ部分も読み上げません。",
+                    listed,
+                    skip::INLINE_MARKER,
+                ),
+            });
+        }
+        _ => return Err(BotError::invalid("Please provide a valid prefix", "接頭辞を入力してください")),
+    }
+
+    store.set_skip_prefixes(guild_id.get(), &prefixes).await?;
+
+    Ok(match language {
+        Language::English => "Skip prefixes updated".to_string(),
+        Language::Japanese => "スキップする接頭辞を更新しました".to_string(),
+    })
+}
+
+/// Sets or clears the role that may manage TTS without Manage Server. Only
+/// members with Manage Server can change it, so the role can't grant itself.
+async fn manager(
+    command: &CommandInteraction,
+    store: &dyn Store,
+    language: Language,
+    args: &[ResolvedOption<'_>],
+) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    require_permissions(command.member.as_deref(), Permissions::MANAGE_GUILD)?;
+
+    let role_id = args.iter().find_map(|option| match option.value {
+        ResolvedValue::Role(role) if option.name == "role" => Some(role.id),
+        _ => None,
+    });
+    store.set_manager_role(guild_id.get(), role_id.map(|id| id.get())).await?;
+
+    Ok(match (language, role_id) {
+        (Language::English, Some(role_id)) => format!("Members with <@&{}> can now manage TTS", role_id),
+        (Language::English, None) => "Only members with Manage Server can manage TTS now".to_string(),
+        (Language::Japanese, Some(role_id)) => format!("<@&{}> を持つメンバーが読み上げを管理できるようになりました", role_id),
+        (Language::Japanese, None) => "読み上げを管理できるのは「サーバー管理」権限を持つメンバーのみになりました".to_string(),
+    })
+}
+
+async fn require_manager(command: &CommandInteraction, ctx: &Context) -> Result<(), BotError> {
+    access::require_manager(ctx, command.guild_id, command.member.as_deref()).await
+}
+
+fn mention(kind: IgnoreKind, target_id: u64) -> String {
+    match kind {
+        IgnoreKind::User => format!("<@{}>", target_id),
+        IgnoreKind::Role => format!("<@&{}>", target_id),
+        IgnoreKind::Channel => format!("<#{}>", target_id),
+    }
+}
+
+pub struct Tts;
+
+#[async_trait]
+impl SlashCommand for Tts {
+    fn name(&self) -> &'static str {
+        "tts"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Control what is read aloud")
+            .description_localized("ja", "読み上げる対象を設定します")
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "optout",
+                "Stop or resume reading your own messages aloud",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommandGroup,
+                    "ignore",
+                    "Add or remove an ignore list entry (TTS managers)",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "user", "Ignore or unignore a user")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::User, "target", "The user")
+                                .required(true),
+                        ),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "role", "Ignore or unignore a role")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::Role, "target", "The role")
+                                .required(true),
+                        ),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "channel", "Ignore or unignore a channel")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::Channel, "target", "The channel")
+                                .required(true),
+                        ),
+                ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "ignored",
+                "List the ignored users, roles and channels",
+            ))
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommandGroup,
+                    "skip",
+                    "Prefixes that keep a message from being read",
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Add a skip prefix (TTS managers)")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::String, "prefix", "e.g. ; or //")
+                                .required(true),
+                        ),
+                )
+                .add_sub_option(
+                    CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Remove a skip prefix (TTS managers)")
+                        .add_sub_option(
+                            CreateCommandOption::new(CommandOptionType::String, "prefix", "The prefix to remove")
+                                .required(true)
+                                .set_autocomplete(true),
+                        ),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "list",
+                    "Show the skip prefixes and the inline marker",
+                )),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "manager",
+                    "Set the role that can manage TTS without Manage Server (leave empty to clear)",
+                )
+                .add_sub_option(CreateCommandOption::new(CommandOptionType::Role, "role", "The TTS manager role")),
+            )
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &[
+            "/tts optout",
+            "/tts ignore channel target:#bot-commands",
+            "/tts skip add prefix:;",
+            "/tts manager role:@TTS",
+        ]
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+
+    /// Suggests the guild's skip prefixes for `/tts skip remove`
+    async fn autocomplete(&self, ctx: &Context, command: &CommandInteraction) -> Result<Vec<AutocompleteChoice>, BotError> {
+        let (Some(guild_id), Some(focused)) = (command.guild_id, command.data.autocomplete()) else {
+            return Ok(Vec::new());
+        };
+        if focused.name != "prefix" {
+            return Ok(Vec::new());
+        }
+        let prefixes = skip::prefixes(ctx, guild_id).await;
+        Ok(autocomplete::choices(focused.value, prefixes.into_iter().map(|p| Candidate::new(p.clone(), p))))
+    }
+}
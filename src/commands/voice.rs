@@ -1,31 +1,21 @@
 use crate::audio;
 use crate::db;
-use serenity::builder::CreateCommand;
-use serenity::builder::EditInteractionResponse;
-use serenity::model::application::CommandInteraction;
+use crate::commands::{Access, BotError, Reply, SlashCommand};
+use serenity::async_trait;
+use crate::commands::autocomplete::{self, Candidate};
+use serenity::builder::{AutocompleteChoice, CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::model::id::ChannelId;
 use serenity::prelude::*;
 use songbird::get;
 use std::fs;
+use std::time::Duration;
+use tracing::{debug, error};
 
-pub fn register() -> CreateCommand {
-    CreateCommand::new("connect").description("Connect to your voice channel")
-}
-
-pub fn register_disconnect() -> CreateCommand {
-    CreateCommand::new("disconnect").description("Disconnect from the voice channel")
-}
-
-pub async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, String> {
-    // Defer the response to show we're processing
-    if let Err(why) = command.defer(&ctx.http).await {
-        println!("❌ Failed to defer response: {}", why);
-        return Err(format!("Failed to defer response: {}", why));
-    }
-    println!("✅ Successfully deferred response");
-
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
     let guild_id = command
         .guild_id
-        .ok_or("This command can only be used in servers")?;
+        .ok_or(BotError::GuildOnly)?;
     let user_id = command.user.id;
     let command_channel_id = command.channel_id;
 
@@ -33,19 +23,20 @@ pub async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String,
     let voice_channel_id = {
         let guild = guild_id
             .to_guild_cached(&ctx.cache)
-            .ok_or("Failed to get guild")?;
+            .ok_or_else(|| BotError::Internal(format!("guild {} is not cached", guild_id)))?;
 
         let voice_state = guild
             .voice_states
             .get(&user_id)
-            .ok_or("You must be in a voice channel")?;
+            .ok_or(BotError::NotInVoiceChannel)?;
 
         voice_state
             .channel_id
-            .ok_or("You must be in a voice channel")?
+            .ok_or(BotError::NotInVoiceChannel)?
     };
 
-    let manager = get(ctx).await.ok_or("Failed to get voice client")?.clone();
+    let manager = get(ctx).await.ok_or_else(no_voice_client)?.clone();
+    let store = db::store(ctx).await;
 
     // Check if already connected
     if let Some(handler_lock) = manager.get(guild_id) {
@@ -53,25 +44,17 @@ pub async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String,
         let mut handler = handler_lock.lock().await;
         if let Some(call) = handler.current_connection() {
             if call.channel_id.map(|id| id.0.get()) == Some(voice_channel_id.get()) {
-                // Bot is already in the same channel, just update the listening status
-                if let Err(e) =
-                    db::set_listening_status(guild_id.get(), command_channel_id.get(), true)
-                {
-                    println!("❌ Failed to store channel in database: {}", e);
-                    return Err(format!("Failed to store channel in database: {}", e));
+                // Bot is already in the same channel, just bind this text channel to the session,
+                // starting one if the call has none (e.g. it was ended while the bot stayed)
+                if !store.bind_text_channel(guild_id.get(), command_channel_id.get()).await? {
+                    store.start_session(guild_id.get(), voice_channel_id.get(), user_id.get()).await?;
+                    store.bind_text_channel(guild_id.get(), command_channel_id.get()).await?;
                 }
-                println!("✅ Successfully updated listening status");
-
-                let builder = EditInteractionResponse::new()
-                    .content("Updated listening status for this channel!");
-                command
-                    .edit_response(&ctx.http, builder)
-                    .await
-                    .map_err(|e| e.to_string())?;
-                return Ok("".to_string());
+                debug!("Successfully bound text channel");
+                return Ok("Now reading this channel too!".to_string());
             } else {
                 // Bot is in a different channel, need to move it
-                handler.leave().await.map_err(|e| e.to_string())?;
+                handler.leave().await.map_err(|e| BotError::Voice(e.to_string()))?;
             }
         }
     }
@@ -79,14 +62,11 @@ pub async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String,
     // Connect to the voice channel
     let _handler = manager.join(guild_id, voice_channel_id).await;
 
-    // Store the command's text channel in the database
-    if let Err(e) = db::set_listening_status(guild_id.get(), command_channel_id.get(), true) {
-        println!("❌ Failed to store channel in database: {}", e);
-        return Err(format!("Failed to store channel in database: {}", e));
-    }
-    println!("✅ Successfully stored channel in database");
+    // Start a session in the voice channel and bind the command's text channel to it
+    store.start_session(guild_id.get(), voice_channel_id.get(), user_id.get()).await?;
+    store.bind_text_channel(guild_id.get(), command_channel_id.get()).await?;
+    debug!("Successfully stored session in database");
 
-    // Edit the deferred response
     if let Ok(handler_lock) = manager.join(guild_id, voice_channel_id).await {
         let mut handler = handler_lock.lock().await;
         if let Err(e) = handler.deafen(true).await {
@@ -94,100 +74,198 @@ pub async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String,
             handler
                 .leave()
                 .await
-                .map_err(|e| format!("Failed to clean up after deafen error: {:?}", e))?;
-            let builder =
-                EditInteractionResponse::new().content(format!("Failed to deafen: {:?}", e));
-            command
-                .edit_response(&ctx.http, builder)
-                .await
-                .map_err(|e| e.to_string())?;
-            return Err(format!("Failed to deafen: {:?}", e));
-        }
-
-        // Update the listening status in the database
-        if let Err(e) = db::set_listening_status(guild_id.get(), voice_channel_id.get(), true) {
-            println!("Failed to update listening status: {:?}", e);
+                .map_err(|e| BotError::Voice(format!("failed to clean up after deafen error: {:?}", e)))?;
+            return Err(BotError::Voice(format!("failed to deafen: {:?}", e)));
         }
 
-        let builder =
-            EditInteractionResponse::new().content("Connected to your voice channel and deafened!");
-        command
-            .edit_response(&ctx.http, builder)
-            .await
-            .map_err(|e| e.to_string())?;
-
         // Get guild language and play appropriate connect audio
         let language =
-            db::get_guild_language(guild_id.get()).unwrap_or(db::language::Language::English);
-        println!("🌐 Current guild language: {:?}", language);
+            store.get_guild_language(guild_id.get()).await.unwrap_or(db::language::Language::English);
+        debug!("Current guild language: {:?}", language);
         let audio_path = audio::get_connect_audio_path(language);
-        println!("📂 Audio path: {:?}", audio_path);
+        debug!("Audio path: {:?}", audio_path);
 
-        // Play the connection audio after sending the message
+        // Play the connection audio once the call has settled
         if let Ok(audio_data) = fs::read(&audio_path) {
-            println!("📊 Audio data size: {} bytes", audio_data.len());
-            let input = songbird::input::Input::try_from(audio_data);
-            if let Ok(input) = input {
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                handler.play_input(input);
-                println!("🎵 Playing connect audio for language: {:?}", language);
-            } else {
-                println!("❌ Failed to create input source from audio data");
-            }
+            debug!("Audio data size: {} bytes", audio_data.len());
+            let input = songbird::input::Input::from(audio_data);
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            handler.play_input(input);
+            debug!("Playing connect audio for language: {:?}", language);
         } else {
-            println!("❌ Failed to read audio file: {:?}", audio_path);
+            error!("Failed to read audio file: {:?}", audio_path);
         }
 
-        Ok("".to_string())
+        Ok("Connected to your voice channel and deafened!".to_string())
     } else {
-        let builder = EditInteractionResponse::new().content("Failed to join voice channel");
-        command
-            .edit_response(&ctx.http, builder)
-            .await
-            .map_err(|e| e.to_string())?;
-        Err("Failed to join voice channel".to_string())
+        Err(BotError::Voice("failed to join voice channel".to_string()))
     }
 }
 
-pub async fn run_disconnect(command: &CommandInteraction, ctx: &Context) -> Result<String, String> {
-    // Defer the response to show we're processing
-    if let Err(why) = command.defer(&ctx.http).await {
-        println!("❌ Failed to defer response: {}", why);
-        return Err(format!("Failed to defer response: {}", why));
-    }
-    println!("✅ Successfully deferred response");
-
+async fn run_disconnect(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
     let guild_id = command
         .guild_id
-        .ok_or("This command can only be used in servers")?;
-    let command_channel_id = command.channel_id;
+        .ok_or(BotError::GuildOnly)?;
 
-    let manager = get(ctx).await.ok_or("Failed to get voice client")?.clone();
+    let manager = get(ctx).await.ok_or_else(no_voice_client)?.clone();
+    let store = db::store(ctx).await;
 
     if let Some(handler_lock) = manager.get(guild_id) {
-        // Remove the command's text channel from the database
-        if let Err(e) = db::set_listening_status(guild_id.get(), command_channel_id.get(), false) {
-            println!("❌ Failed to update database: {}", e);
-            return Err(format!("Failed to update database: {}", e));
-        }
-        println!("✅ Successfully removed channel from database");
+        // Leaving ends the session along with all of its bound text channels
+        store.end_session(guild_id.get()).await?;
+        debug!("Successfully ended session");
 
         // Disconnect from the voice channel
         let mut handler = handler_lock.lock().await;
-        handler.leave().await.map_err(|e| e.to_string())?;
-
-        let builder = EditInteractionResponse::new().content("Disconnected from voice channel!");
-        command
-            .edit_response(&ctx.http, builder)
-            .await
-            .map_err(|e| e.to_string())?;
-        Ok("".to_string())
+        handler.leave().await.map_err(|e| BotError::Voice(e.to_string()))?;
+        Ok("Disconnected from voice channel!".to_string())
     } else {
-        let builder = EditInteractionResponse::new().content("Not connected to a voice channel!");
-        command
-            .edit_response(&ctx.http, builder)
-            .await
-            .map_err(|e| e.to_string())?;
-        Ok("".to_string())
+        Ok("Not connected to a voice channel!".to_string())
+    }
+}
+
+async fn run_unbind(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command
+        .guild_id
+        .ok_or(BotError::GuildOnly)?;
+    let store = db::store(ctx).await;
+
+    // Autocomplete suggests the bound channels by ID
+    let channel_id = match command.data.options().first().map(|option| &option.value) {
+        Some(ResolvedValue::String(channel)) => channel
+            .parse::<u64>()
+            .ok()
+            .filter(|id| *id != 0)
+            .map(ChannelId::new)
+            .ok_or_else(|| BotError::invalid("Please pick a channel from the list", "一覧からチャンネルを選んでください"))?,
+        _ => command.channel_id,
+    };
+
+    if store.unbind_text_channel(guild_id.get(), channel_id.get()).await? {
+        Ok(if channel_id == command.channel_id {
+            "Stopped reading this channel!".to_string()
+        } else {
+            format!("Stopped reading <#{}>!", channel_id)
+        })
+    } else {
+        Ok("This channel is not being read!".to_string())
+    }
+}
+
+fn no_voice_client() -> BotError {
+    BotError::Internal("songbird is not registered with the client".to_string())
+}
+
+pub struct Connect;
+
+#[async_trait]
+impl SlashCommand for Connect {
+    fn name(&self) -> &'static str {
+        "connect"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Connect to your voice channel")
+            .description_localized("ja", "参加中のボイスチャンネルに接続します")
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+
+    fn access(&self) -> Access {
+        Access::Manager
+    }
+
+    /// Joining takes a moment, don't let the bot be bounced between channels
+    fn guild_cooldown(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+
+    fn defers(&self) -> bool {
+        true
+    }
+}
+
+pub struct Disconnect;
+
+#[async_trait]
+impl SlashCommand for Disconnect {
+    fn name(&self) -> &'static str {
+        "disconnect"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Disconnect from the voice channel")
+            .description_localized("ja", "ボイスチャンネルから切断します")
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run_disconnect(command, ctx).await.map(Reply::from)
+    }
+
+    fn access(&self) -> Access {
+        Access::Manager
+    }
+
+    fn guild_cooldown(&self) -> Option<Duration> {
+        Some(Duration::from_secs(5))
+    }
+
+    fn defers(&self) -> bool {
+        true
+    }
+}
+
+pub struct Unbind;
+
+#[async_trait]
+impl SlashCommand for Unbind {
+    fn name(&self) -> &'static str {
+        "unbind"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Stop reading this text channel without leaving the voice channel")
+            .description_localized("ja", "ボイスチャンネルに残ったままこのチャンネルの読み上げをやめます")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "channel", "A read channel other than this one")
+                    .set_autocomplete(true),
+            )
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run_unbind(command, ctx).await.map(Reply::from)
+    }
+
+    fn access(&self) -> Access {
+        Access::Manager
+    }
+
+    /// Suggests the text channels bound to the guild's session
+    async fn autocomplete(&self, ctx: &Context, command: &CommandInteraction) -> Result<Vec<AutocompleteChoice>, BotError> {
+        let (Some(guild_id), Some(focused)) = (command.guild_id, command.data.autocomplete()) else {
+            return Ok(Vec::new());
+        };
+        let Some(session) = db::store(ctx).await.get_session(guild_id.get()).await? else {
+            return Ok(Vec::new());
+        };
+        let guild = guild_id.to_guild_cached(&ctx.cache);
+        let candidates = session
+            .text_channel_ids
+            .into_iter()
+            .map(|channel_id| {
+                let name = guild
+                    .as_ref()
+                    .and_then(|guild| guild.channels.get(&ChannelId::new(channel_id)))
+                    .map(|channel| format!("#{}", channel.name))
+                    .unwrap_or_else(|| channel_id.to_string());
+                Candidate::new(name, channel_id.to_string())
+            })
+            .collect::<Vec<_>>();
+        Ok(autocomplete::choices(focused.value, candidates))
     }
 }
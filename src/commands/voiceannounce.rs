@@ -0,0 +1,90 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedValue};
+use serenity::prelude::*;
+use serenity::async_trait;
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+use crate::db::VoiceAnnounceSettings;
+
+const MAX_DEBOUNCE_SECS: i64 = 30;
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let store = crate::db::store(ctx).await;
+
+    let mut settings = store.get_voice_announce_settings(guild_id.get()).await?;
+
+    let options = command.data.options();
+    for option in &options {
+        match (option.name, &option.value) {
+            ("joins", ResolvedValue::Boolean(enabled)) => settings.joins = *enabled,
+            ("leaves", ResolvedValue::Boolean(enabled)) => settings.leaves = *enabled,
+            ("moves", ResolvedValue::Boolean(enabled)) => settings.moves = *enabled,
+            ("streams", ResolvedValue::Boolean(enabled)) => settings.streams = *enabled,
+            ("video", ResolvedValue::Boolean(enabled)) => settings.video = *enabled,
+            ("debounce", ResolvedValue::Integer(secs)) => {
+                settings.debounce_secs = (*secs).clamp(0, MAX_DEBOUNCE_SECS) as u64
+            }
+            _ => {}
+        }
+    }
+
+    if !options.is_empty() {
+        store.set_voice_announce_settings(guild_id.get(), settings).await?;
+    }
+
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+    Ok(describe(language, &settings))
+}
+
+fn describe(language: Language, settings: &VoiceAnnounceSettings) -> String {
+    let mark = |enabled: bool| if enabled { "✅" } else { "❌" };
+    let (labels, debounce) = match language {
+        Language::English => (
+            ["Joins", "Leaves", "Moves", "Screen share", "Camera"],
+            format!("Debounce: {}s", settings.debounce_secs),
+        ),
+        Language::Japanese => (
+            ["入室", "退室", "移動", "画面共有", "カメラ"],
+            format!("待機時間: {}秒", settings.debounce_secs),
+        ),
+    };
+    let values = [settings.joins, settings.leaves, settings.moves, settings.streams, settings.video];
+
+    labels
+        .iter()
+        .zip(values)
+        .map(|(label, enabled)| format!("{} {}", mark(enabled), label))
+        .chain(std::iter::once(debounce))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub struct VoiceAnnounce;
+
+#[async_trait]
+impl SlashCommand for VoiceAnnounce {
+    fn name(&self) -> &'static str {
+        "voiceannounce"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Choose which voice channel events are announced (no options shows the current settings)")
+            .description_localized("ja", "読み上げるボイスチャンネルのイベントを選びます（オプションなしで現在の設定を表示）")
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "joins", "Announce members joining"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "leaves", "Announce members leaving"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "moves", "Announce members moving in or out"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "streams", "Announce screen share start/stop"))
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "video", "Announce camera on/off"))
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Integer, "debounce", "Seconds a member's state must settle before it is announced")
+                    .min_int_value(0)
+                    .max_int_value(MAX_DEBOUNCE_SECS as u64),
+            )
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+}
@@ -0,0 +1,96 @@
+use serenity::builder::{AutocompleteChoice, CreateCommand, CreateCommandOption};
+use serenity::model::application::{CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue};
+use serenity::prelude::*;
+use serenity::async_trait;
+use crate::commands::autocomplete::{self, Candidate};
+use crate::commands::{BotError, Reply, SlashCommand};
+use crate::db::language::Language;
+use crate::tts::voices;
+
+async fn run(command: &CommandInteraction, ctx: &Context) -> Result<String, BotError> {
+    let guild_id = command.guild_id.ok_or(BotError::GuildOnly)?;
+    let store = crate::db::store(ctx).await;
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+    let mut settings = store.get_speech_settings(guild_id.get()).await?;
+
+    let options = command.data.options();
+    let Some(ResolvedOption { name: subcommand, value: ResolvedValue::SubCommand(args), .. }) = options.first() else {
+        return Err(BotError::invalid("Please choose set or list", "set・list のいずれかを選んでください"));
+    };
+
+    match *subcommand {
+        "set" => {
+            let id = args.iter().find_map(|option| match option.value {
+                ResolvedValue::String(id) if option.name == "voice" => Some(id.trim()),
+                _ => None,
+            });
+            let voice = voices::all()
+                .into_iter()
+                .find(|voice| Some(voice.id.as_str()) == id)
+                .ok_or_else(|| BotError::invalid("Please pick a voice from the list", "一覧から声を選んでください"))?;
+
+            settings.voice = (voice.id != voices::DEFAULT).then(|| voice.id.clone());
+            store.set_speech_settings(guild_id.get(), settings).await?;
+            Ok(match language {
+                Language::English => format!("Messages will be read by {}", voice.name),
+                Language::Japanese => format!("{} の声で読み上げます", voice.name),
+            })
+        }
+        "list" => {
+            let current = voices::find(settings.voice.as_deref());
+            Ok(voices::all()
+                .iter()
+                .map(|voice| {
+                    let marker = if voice.id == current.id { " ✅" } else { "" };
+                    format!("{} (`{}`){}", voice.name, voice.id, marker)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        _ => Err(BotError::Internal(format!("unknown subcommand /voice {}", subcommand))),
+    }
+}
+
+pub struct VoiceCommand;
+
+#[async_trait]
+impl SlashCommand for VoiceCommand {
+    fn name(&self) -> &'static str {
+        "voice"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("Choose the voice messages are read in")
+            .description_localized("ja", "読み上げる声を選びます")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Change the server's voice")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "voice", "The voice to use")
+                            .required(true)
+                            .set_autocomplete(true),
+                    ),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "list", "List the available voices"))
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &[
+            "/voice set voice:hiroyuki",
+            "/voice list",
+        ]
+    }
+
+    async fn execute(&self, ctx: &Context, command: &CommandInteraction) -> Result<Reply, BotError> {
+        run(command, ctx).await.map(Reply::from)
+    }
+
+    /// Suggests the configured voices for `/voice set`
+    async fn autocomplete(&self, _ctx: &Context, command: &CommandInteraction) -> Result<Vec<AutocompleteChoice>, BotError> {
+        let Some(focused) = command.data.autocomplete() else {
+            return Ok(Vec::new());
+        };
+        let candidates = voices::all().into_iter().map(|voice| Candidate::new(voice.name, voice.id));
+        Ok(autocomplete::choices(focused.value, candidates))
+    }
+}
@@ -1,5 +1,25 @@
 use serenity::builder::CreateCommand;
+use serenity::model::application::CommandInteraction;
+use serenity::async_trait;
+use serenity::prelude::*;
 
-pub fn register() -> CreateCommand {
-    CreateCommand::new("wonderful_command").description("An amazing command")
-}
\ No newline at end of file
+use crate::commands::{BotError, Reply, SlashCommand};
+
+pub struct WonderfulCommand;
+
+#[async_trait]
+impl SlashCommand for WonderfulCommand {
+    fn name(&self) -> &'static str {
+        "wonderful_command"
+    }
+
+    fn register(&self) -> CreateCommand {
+        CreateCommand::new(self.name())
+            .description("An amazing command")
+            .description_localized("ja", "すごいコマンド")
+    }
+
+    async fn execute(&self, _ctx: &Context, _command: &CommandInteraction) -> Result<Reply, BotError> {
+        Ok(Reply::from("not implemented :("))
+    }
+}
@@ -0,0 +1,616 @@
+//! Typed bot configuration.
+//!
+//! Settings are read from a TOML file (`config.toml` unless `--config` says
+//! otherwise), then overridden by `BOT_*` environment variables, e.g.
+//! `BOT_DISCORD_TOKEN` or `BOT_DATABASE_PATH`. Every setting has a default
+//! except the Discord token, so the file is optional.
+
+use serde::Deserialize;
+use serenity::model::gateway::GatewayIntents;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Read when no `--config` is given. Unlike an explicit path it may be missing.
+pub const DEFAULT_PATH: &str = "config.toml";
+
+const ENV_PREFIX: &str = "BOT_";
+
+/// The bot can't join calls or see channels without these.
+const REQUIRED_INTENTS: &[&str] = &["GUILDS", "GUILD_VOICE_STATES"];
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub discord: DiscordConfig,
+    pub commands: CommandsConfig,
+    pub sharding: ShardingConfig,
+    pub database: DatabaseConfig,
+    pub audio: AudioConfig,
+    pub tts: TtsConfig,
+    pub shutdown: ShutdownConfig,
+    pub logging: LoggingConfig,
+    pub http: HttpConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub token: String,
+    /// Gateway intent names, e.g. `GUILD_MESSAGES`.
+    pub intents: Vec<String>,
+}
+
+impl Default for DiscordConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            intents: ["GUILDS", "GUILD_VOICE_STATES", "GUILD_MESSAGES", "MESSAGE_CONTENT", "GUILD_MESSAGE_REACTIONS"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandsConfig {
+    /// Development mode: register the slash commands in these guilds only,
+    /// where changes show up immediately, and leave the global ones alone.
+    pub dev_guilds: Vec<u64>,
+    /// Users who may run owner-only commands, besides the application's
+    /// owner or team members.
+    pub owners: Vec<u64>,
+}
+
+impl CommandsConfig {
+    pub fn dev_mode(&self) -> bool {
+        !self.dev_guilds.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardingMode {
+    /// One shard, for bots in fewer than 2,500 guilds.
+    Single,
+    /// As many shards as Discord recommends, all in this process.
+    Auto,
+    /// Shards `first..=last` out of `total`, to split the bot across processes.
+    Range,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShardingConfig {
+    pub mode: ShardingMode,
+    /// Only used with `mode = "range"`.
+    pub first: u32,
+    pub last: u32,
+    pub total: u32,
+}
+
+impl Default for ShardingConfig {
+    fn default() -> Self {
+        Self {
+            mode: ShardingMode::Auto,
+            first: 0,
+            last: 0,
+            total: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub path: PathBuf,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self { path: PathBuf::from("bot.db") }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// Where the generated connect sounds are cached.
+    pub assets_dir: PathBuf,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self { assets_dir: PathBuf::from("assets/audio") }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TtsConfig {
+    /// The Hiroyuki endpoint that returns the location of the synthesized audio.
+    pub api_url: String,
+    /// How many recent utterances to keep synthesized in memory. 0 disables the cache.
+    pub cache_entries: usize,
+    /// Consecutive backend failures before synthesis is paused.
+    pub circuit_failure_threshold: u32,
+    /// How long synthesis stays paused before the backend is tried again.
+    pub circuit_cooldown_secs: u64,
+    /// Voices guilds can pick in `/settings` besides the default one at `api_url`.
+    pub voices: Vec<VoiceConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VoiceConfig {
+    /// Stored with the guild's settings, so keep it stable.
+    pub id: String,
+    pub name: String,
+    /// An endpoint that works like `tts.api_url`.
+    pub api_url: String,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        Self {
+            api_url: "https://plbwpbyme3.execute-api.ap-northeast-1.amazonaws.com/production/coefonts/19d55439-312d-4a1d-a27b-28f0f31bedc5/try".to_string(),
+            cache_entries: 256,
+            circuit_failure_threshold: 5,
+            circuit_cooldown_secs: 30,
+            voices: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownConfig {
+    /// How long the current utterances may keep playing after SIGTERM/SIGINT.
+    pub drain_timeout_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { drain_timeout_secs: 8 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// Keep message text and spoken content out of the logs.
+    pub redact_content: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Text,
+            redact_content: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    /// Where `/metrics`, `/healthz` and `/readyz` are served.
+    pub listen: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            listen: "127.0.0.1:9100".to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, source: std::io::Error },
+    Parse { path: PathBuf, source: toml::de::Error },
+    /// Every problem found while validating, so they can be fixed in one go.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => write!(f, "failed to read {}: {}", path.display(), source),
+            ConfigError::Parse { path, source } => write!(f, "failed to parse {}: {}", path.display(), source),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the file at `path` (or `config.toml` if it exists), applies the
+    /// environment overrides and validates the result.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_PATH).exists() => Self::from_file(Path::new(DEFAULT_PATH))?,
+            None => Self::default(),
+        };
+
+        // The token used to come from `DISCORD_TOKEN`, keep honoring it
+        if config.discord.token.is_empty() {
+            if let Ok(token) = std::env::var("DISCORD_TOKEN") {
+                config.discord.token = token;
+            }
+        }
+
+        let mut problems = config.apply_env(|key| std::env::var(key).ok());
+        problems.extend(config.validate());
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Overrides settings from `BOT_<SECTION>_<KEY>` variables looked up with
+    /// `var`. Returns the variables that couldn't be parsed.
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+        let var = |key: &str| var(&format!("{}{}", ENV_PREFIX, key));
+        let mut problems = Vec::new();
+
+        if let Some(token) = var("DISCORD_TOKEN") {
+            self.discord.token = token;
+        }
+        if let Some(intents) = var("DISCORD_INTENTS") {
+            self.discord.intents = intents
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+        }
+        if let Some(guilds) = var("COMMANDS_DEV_GUILDS") {
+            match parse_ids(&guilds) {
+                Ok(guilds) => self.commands.dev_guilds = guilds,
+                Err(id) => problems.push(format!("{}COMMANDS_DEV_GUILDS must list guild IDs, got {:?}", ENV_PREFIX, id)),
+            }
+        }
+        if let Some(owners) = var("COMMANDS_OWNERS") {
+            match parse_ids(&owners) {
+                Ok(owners) => self.commands.owners = owners,
+                Err(id) => problems.push(format!("{}COMMANDS_OWNERS must list user IDs, got {:?}", ENV_PREFIX, id)),
+            }
+        }
+        if let Some(mode) = var("SHARDING_MODE") {
+            match mode.to_lowercase().as_str() {
+                "single" => self.sharding.mode = ShardingMode::Single,
+                "auto" => self.sharding.mode = ShardingMode::Auto,
+                "range" => self.sharding.mode = ShardingMode::Range,
+                _ => problems.push(format!("{}SHARDING_MODE must be \"single\", \"auto\" or \"range\", got {:?}", ENV_PREFIX, mode)),
+            }
+        }
+        for (key, field) in [
+            ("SHARDING_FIRST", &mut self.sharding.first),
+            ("SHARDING_LAST", &mut self.sharding.last),
+            ("SHARDING_TOTAL", &mut self.sharding.total),
+        ] {
+            if let Some(value) = var(key) {
+                match value.parse() {
+                    Ok(value) => *field = value,
+                    Err(_) => problems.push(format!("{}{} must be a whole number, got {:?}", ENV_PREFIX, key, value)),
+                }
+            }
+        }
+        if let Some(path) = var("DATABASE_PATH") {
+            self.database.path = PathBuf::from(path);
+        }
+        if let Some(dir) = var("AUDIO_ASSETS_DIR") {
+            self.audio.assets_dir = PathBuf::from(dir);
+        }
+        if let Some(url) = var("TTS_API_URL") {
+            self.tts.api_url = url;
+        }
+        if let Some(entries) = var("TTS_CACHE_ENTRIES") {
+            match entries.parse() {
+                Ok(entries) => self.tts.cache_entries = entries,
+                Err(_) => problems.push(format!("{}TTS_CACHE_ENTRIES must be a whole number, got {:?}", ENV_PREFIX, entries)),
+            }
+        }
+        if let Some(threshold) = var("TTS_CIRCUIT_FAILURE_THRESHOLD") {
+            match threshold.parse() {
+                Ok(threshold) => self.tts.circuit_failure_threshold = threshold,
+                Err(_) => problems.push(format!("{}TTS_CIRCUIT_FAILURE_THRESHOLD must be a whole number, got {:?}", ENV_PREFIX, threshold)),
+            }
+        }
+        if let Some(secs) = var("TTS_CIRCUIT_COOLDOWN_SECS") {
+            match secs.parse() {
+                Ok(secs) => self.tts.circuit_cooldown_secs = secs,
+                Err(_) => problems.push(format!("{}TTS_CIRCUIT_COOLDOWN_SECS must be a whole number of seconds, got {:?}", ENV_PREFIX, secs)),
+            }
+        }
+        if let Some(secs) = var("SHUTDOWN_DRAIN_TIMEOUT_SECS") {
+            match secs.parse() {
+                Ok(secs) => self.shutdown.drain_timeout_secs = secs,
+                Err(_) => problems.push(format!(
+                    "{}SHUTDOWN_DRAIN_TIMEOUT_SECS must be a whole number of seconds, got {:?}",
+                    ENV_PREFIX, secs
+                )),
+            }
+        }
+
+        if let Some(format) = var("LOGGING_FORMAT") {
+            match format.to_lowercase().as_str() {
+                "text" => self.logging.format = LogFormat::Text,
+                "json" => self.logging.format = LogFormat::Json,
+                _ => problems.push(format!("{}LOGGING_FORMAT must be \"text\" or \"json\", got {:?}", ENV_PREFIX, format)),
+            }
+        }
+        if let Some(redact) = var("LOGGING_REDACT_CONTENT") {
+            match parse_bool(&redact) {
+                Some(redact) => self.logging.redact_content = redact,
+                None => problems.push(format!("{}LOGGING_REDACT_CONTENT must be true or false, got {:?}", ENV_PREFIX, redact)),
+            }
+        }
+        if let Some(enabled) = var("HTTP_ENABLED") {
+            match parse_bool(&enabled) {
+                Some(enabled) => self.http.enabled = enabled,
+                None => problems.push(format!("{}HTTP_ENABLED must be true or false, got {:?}", ENV_PREFIX, enabled)),
+            }
+        }
+        if let Some(listen) = var("HTTP_LISTEN") {
+            self.http.listen = listen;
+        }
+
+        problems
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.discord.token.trim().is_empty() {
+            problems.push(format!(
+                "discord.token is not set (set it in the config file or {}DISCORD_TOKEN)",
+                ENV_PREFIX
+            ));
+        }
+        for name in &self.discord.intents {
+            if GatewayIntents::from_name(name).is_none() {
+                problems.push(format!("discord.intents: unknown gateway intent {:?}", name));
+            }
+        }
+        for name in REQUIRED_INTENTS {
+            if !self.discord.intents.iter().any(|intent| intent == name) {
+                problems.push(format!("discord.intents must include {}", name));
+            }
+        }
+        if self.commands.dev_guilds.contains(&0) {
+            problems.push("commands.dev_guilds must only contain guild IDs, got 0".to_string());
+        }
+        if self.commands.owners.contains(&0) {
+            problems.push("commands.owners must only contain user IDs, got 0".to_string());
+        }
+        if self.sharding.mode == ShardingMode::Range {
+            let ShardingConfig { first, last, total, .. } = self.sharding;
+            if total == 0 {
+                problems.push("sharding.total must be at least 1".to_string());
+            } else if first > last || last >= total {
+                problems.push(format!(
+                    "sharding.first..=sharding.last must be within 0..{}, got {}..={}",
+                    total, first, last
+                ));
+            }
+        }
+        if self.database.path.as_os_str().is_empty() {
+            problems.push("database.path must not be empty".to_string());
+        }
+        if self.audio.assets_dir.as_os_str().is_empty() {
+            problems.push("audio.assets_dir must not be empty".to_string());
+        }
+        if !self.tts.api_url.starts_with("https://") && !self.tts.api_url.starts_with("http://") {
+            problems.push(format!("tts.api_url must be an http(s) URL, got {:?}", self.tts.api_url));
+        }
+        for (i, voice) in self.tts.voices.iter().enumerate() {
+            if voice.id.is_empty() || voice.id == crate::tts::voices::DEFAULT {
+                problems.push(format!(
+                    "tts.voices: id must be set and not {:?}, got {:?}",
+                    crate::tts::voices::DEFAULT, voice.id
+                ));
+            } else if self.tts.voices[..i].iter().any(|other| other.id == voice.id) {
+                problems.push(format!("tts.voices: {:?} is listed twice", voice.id));
+            }
+            if !voice.api_url.starts_with("https://") && !voice.api_url.starts_with("http://") {
+                problems.push(format!("tts.voices: api_url of {:?} must be an http(s) URL", voice.id));
+            }
+        }
+        if self.tts.circuit_failure_threshold == 0 {
+            problems.push("tts.circuit_failure_threshold must be at least 1".to_string());
+        }
+        if self.http.enabled && self.http.listen.parse::<SocketAddr>().is_err() {
+            problems.push(format!("http.listen must be an address like 127.0.0.1:9100, got {:?}", self.http.listen));
+        }
+        if self.shutdown.drain_timeout_secs > 60 {
+            problems.push("shutdown.drain_timeout_secs must be at most 60".to_string());
+        }
+
+        problems
+    }
+
+    /// The configured intents. Only meaningful once the config is validated.
+    pub fn intents(&self) -> GatewayIntents {
+        self.discord.intents
+            .iter()
+            .filter_map(|name| GatewayIntents::from_name(name))
+            .fold(GatewayIntents::empty(), |intents, intent| intents | intent)
+    }
+}
+
+/// A comma-separated list of IDs, or the first entry that isn't one.
+fn parse_ids(value: &str) -> Result<Vec<u64>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse().map_err(|_| id.to_string()))
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Makes `config` available through [`get`]. Only the first call has an effect.
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The configuration the bot was started with, or the defaults before [`init`].
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid() -> Config {
+        let mut config = Config::default();
+        config.discord.token = "token".to_string();
+        config
+    }
+
+    #[test]
+    fn defaults_need_only_a_token() {
+        assert_eq!(valid().validate(), Vec::<String>::new());
+        assert_eq!(Config::default().validate().len(), 1);
+    }
+
+    #[test]
+    fn file_sections_are_optional() {
+        let config = Config::parse("[database]\npath = \"/data/bot.db\"\n").unwrap();
+
+        assert_eq!(config.database.path, PathBuf::from("/data/bot.db"));
+        assert_eq!(config.audio.assets_dir, PathBuf::from("assets/audio"));
+        assert_eq!(config.intents(), GatewayIntents::GUILDS
+            | GatewayIntents::GUILD_VOICE_STATES
+            | GatewayIntents::GUILD_MESSAGES
+            | GatewayIntents::MESSAGE_CONTENT
+            | GatewayIntents::GUILD_MESSAGE_REACTIONS);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(Config::parse("[database]\npaht = \"bot.db\"\n").is_err());
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let mut config = Config::parse("[discord]\ntoken = \"from-file\"\n").unwrap();
+        let problems = config.apply_env(|key| match key {
+            "BOT_DISCORD_TOKEN" => Some("from-env".to_string()),
+            "BOT_DISCORD_INTENTS" => Some("GUILDS, GUILD_VOICE_STATES".to_string()),
+            "BOT_SHUTDOWN_DRAIN_TIMEOUT_SECS" => Some("3".to_string()),
+            "BOT_LOGGING_FORMAT" => Some("JSON".to_string()),
+            "BOT_LOGGING_REDACT_CONTENT" => Some("off".to_string()),
+            "BOT_HTTP_LISTEN" => Some("0.0.0.0:9200".to_string()),
+            "BOT_COMMANDS_DEV_GUILDS" => Some("123, 456".to_string()),
+            "BOT_COMMANDS_OWNERS" => Some("789".to_string()),
+            _ => None,
+        });
+
+        assert!(problems.is_empty());
+        assert_eq!(config.commands.dev_guilds, vec![123, 456]);
+        assert!(config.commands.dev_mode());
+        assert_eq!(config.commands.owners, vec![789]);
+        assert_eq!(config.logging.format, LogFormat::Json);
+        assert!(!config.logging.redact_content);
+        assert_eq!(config.http.listen, "0.0.0.0:9200");
+        assert_eq!(config.discord.token, "from-env");
+        assert_eq!(config.intents(), GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES);
+        assert_eq!(config.shutdown.drain_timeout_secs, 3);
+    }
+
+    #[test]
+    fn invalid_settings_are_all_reported() {
+        let mut config = valid();
+        let problems = config.apply_env(|key| match key {
+            "BOT_SHUTDOWN_DRAIN_TIMEOUT_SECS" => Some("soon".to_string()),
+            _ => None,
+        });
+        assert_eq!(problems.len(), 1);
+
+        config.discord.intents = vec!["GUILDS".to_string(), "GUILD_NOPE".to_string()];
+        config.tts.api_url = "ftp://example.com".to_string();
+        let problems = config.validate();
+
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.contains("GUILD_NOPE")));
+        assert!(problems.iter().any(|p| p.contains("GUILD_VOICE_STATES")));
+        assert!(problems.iter().any(|p| p.contains("tts.api_url")));
+    }
+
+    #[test]
+    fn voices_need_unique_ids_and_urls() {
+        let mut config = Config::parse(
+            "[[tts.voices]]\nid = \"calm\"\nname = \"Calm\"\napi_url = \"https://example.com/calm\"\n",
+        )
+        .unwrap();
+        config.discord.token = "token".to_string();
+        assert!(config.validate().is_empty());
+
+        let mut duplicate = config.tts.voices[0].clone();
+        duplicate.api_url = "example.com".to_string();
+        config.tts.voices.push(duplicate);
+        assert_eq!(config.validate().len(), 2, "{:?}", config.validate());
+    }
+
+    #[test]
+    fn shard_ranges_must_fit_the_total() {
+        let mut config = Config::parse("[sharding]\nmode = \"range\"\nfirst = 2\nlast = 3\ntotal = 4\n").unwrap();
+        config.discord.token = "token".to_string();
+        assert!(config.validate().is_empty());
+
+        config.sharding.last = 4;
+        assert_eq!(config.validate().len(), 1);
+        config.sharding.first = 5;
+        assert_eq!(config.validate().len(), 1);
+    }
+}
@@ -0,0 +1,16 @@
+-- A database as created by the bot before schema versioning.
+CREATE TABLE voice_channels (
+    guild_id INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    is_listening BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY (guild_id, channel_id)
+);
+CREATE TABLE guild_settings (
+    guild_id INTEGER PRIMARY KEY,
+    language TEXT NOT NULL DEFAULT 'english'
+);
+
+INSERT INTO voice_channels (guild_id, channel_id, is_listening) VALUES (1, 100, 1);
+INSERT INTO voice_channels (guild_id, channel_id, is_listening) VALUES (1, 200, 0);
+INSERT INTO guild_settings (guild_id, language) VALUES (1, 'japanese');
+INSERT INTO guild_settings (guild_id, language) VALUES (2, 'english');
@@ -0,0 +1,280 @@
+//! Ordered schema migrations, tracked through `PRAGMA user_version`.
+//!
+//! Each migration runs in its own transaction together with the version bump,
+//! so a failing step leaves the database at the previous version. Databases
+//! created before versioning report version 0 and may already contain some of
+//! the tables, which is why the early steps use `IF NOT EXISTS`.
+
+use rusqlite::Connection;
+use std::fmt;
+use tracing::info;
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: "
+            CREATE TABLE IF NOT EXISTS voice_channels (
+                guild_id INTEGER NOT NULL,
+                channel_id INTEGER NOT NULL,
+                is_listening BOOLEAN NOT NULL DEFAULT 0,
+                PRIMARY KEY (guild_id, channel_id)
+            );
+            CREATE TABLE IF NOT EXISTS guild_settings (
+                guild_id INTEGER PRIMARY KEY,
+                language TEXT NOT NULL DEFAULT 'english'
+            );
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "reading settings, names, dictionary and filters",
+        sql: "
+            CREATE TABLE IF NOT EXISTS name_announce_settings (
+                guild_id INTEGER PRIMARY KEY,
+                mode TEXT NOT NULL DEFAULT 'never'
+            );
+            CREATE TABLE IF NOT EXISTS spoken_names (
+                guild_id INTEGER NOT NULL,
+                user_id INTEGER NOT NULL,
+                reading TEXT NOT NULL,
+                PRIMARY KEY (guild_id, user_id)
+            );
+            CREATE TABLE IF NOT EXISTS dictionary (
+                guild_id INTEGER NOT NULL,
+                word TEXT NOT NULL,
+                reading TEXT NOT NULL,
+                PRIMARY KEY (guild_id, word)
+            );
+            CREATE TABLE IF NOT EXISTS read_settings (
+                guild_id INTEGER PRIMARY KEY,
+                replies BOOLEAN NOT NULL DEFAULT 1,
+                attachments BOOLEAN NOT NULL DEFAULT 1,
+                stickers BOOLEAN NOT NULL DEFAULT 1,
+                embeds BOOLEAN NOT NULL DEFAULT 1,
+                polls BOOLEAN NOT NULL DEFAULT 1
+            );
+            CREATE TABLE IF NOT EXISTS voice_announce_settings (
+                guild_id INTEGER PRIMARY KEY,
+                joins BOOLEAN NOT NULL DEFAULT 1,
+                leaves BOOLEAN NOT NULL DEFAULT 1,
+                moves BOOLEAN NOT NULL DEFAULT 1,
+                streams BOOLEAN NOT NULL DEFAULT 0,
+                video BOOLEAN NOT NULL DEFAULT 0,
+                debounce_secs INTEGER NOT NULL DEFAULT 3
+            );
+            CREATE TABLE IF NOT EXISTS tts_optouts (
+                user_id INTEGER PRIMARY KEY
+            );
+            CREATE TABLE IF NOT EXISTS tts_ignores (
+                guild_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                target_id INTEGER NOT NULL,
+                PRIMARY KEY (guild_id, kind, target_id)
+            );
+            CREATE TABLE IF NOT EXISTS skip_settings (
+                guild_id INTEGER PRIMARY KEY,
+                prefixes TEXT NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "voice sessions with bound text channels",
+        sql: "
+            DROP TABLE IF EXISTS voice_channels;
+            CREATE TABLE IF NOT EXISTS voice_sessions (
+                guild_id INTEGER PRIMARY KEY,
+                voice_channel_id INTEGER NOT NULL,
+                started_at INTEGER NOT NULL,
+                started_by INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS session_text_channels (
+                guild_id INTEGER NOT NULL REFERENCES voice_sessions (guild_id) ON DELETE CASCADE,
+                channel_id INTEGER NOT NULL,
+                PRIMARY KEY (guild_id, channel_id)
+            );
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "voice and length limit per guild",
+        sql: "
+            CREATE TABLE IF NOT EXISTS speech_settings (
+                guild_id INTEGER PRIMARY KEY,
+                voice TEXT,
+                max_chars INTEGER NOT NULL DEFAULT 120
+            );
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "TTS manager role per guild",
+        sql: "
+            CREATE TABLE IF NOT EXISTS manager_roles (
+                guild_id INTEGER PRIMARY KEY,
+                role_id INTEGER NOT NULL
+            );
+        ",
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    /// The database was migrated by a newer build of the bot.
+    DatabaseTooNew { database: u32, supported: u32 },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "{}", e),
+            MigrationError::DatabaseTooNew { database, supported } => write!(
+                f,
+                "database schema version {} is newer than the latest version {} this binary supports",
+                database, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+pub fn user_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Brings the database up to the latest schema version.
+pub fn run(conn: &mut Connection) -> Result<(), MigrationError> {
+    apply(conn, MIGRATIONS)
+}
+
+fn apply(conn: &mut Connection, migrations: &[Migration]) -> Result<(), MigrationError> {
+    let current = user_version(conn)?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    if current > latest {
+        return Err(MigrationError::DatabaseTooNew { database: current, supported: latest });
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        info!("Migrating database to version {}: {}", migration.version, migration.description);
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    info!("Database schema is at version {}", latest);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latest_version() -> u32 {
+        MIGRATIONS.last().map(|m| m.version).unwrap()
+    }
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+            [name],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn versions_are_ordered() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, i + 1);
+        }
+    }
+
+    #[test]
+    fn migrates_fixture_from_version_0() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("fixtures/v0.sql")).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), 0);
+
+        run(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        assert!(!table_exists(&conn, "voice_channels"));
+        for table in ["guild_settings", "voice_sessions", "session_text_channels", "dictionary", "tts_ignores"] {
+            assert!(table_exists(&conn, table), "missing table {}", table);
+        }
+
+        // Existing settings survive the migration
+        let language: String = conn
+            .query_row("SELECT language FROM guild_settings WHERE guild_id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(language, "japanese");
+    }
+
+    #[test]
+    fn migrates_empty_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        run(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        assert!(table_exists(&conn, "voice_sessions"));
+    }
+
+    #[test]
+    fn running_twice_is_a_no_op() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        conn.execute("INSERT INTO guild_settings (guild_id, language) VALUES (1, 'japanese')", []).unwrap();
+
+        run(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM guild_settings", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+
+        match run(&mut conn) {
+            Err(MigrationError::DatabaseTooNew { database, supported }) => {
+                assert_eq!(database, latest_version() + 1);
+                assert_eq!(supported, latest_version());
+            }
+            other => panic!("expected DatabaseTooNew, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_migration_is_rolled_back() {
+        let migrations = [
+            Migration { version: 1, description: "ok", sql: "CREATE TABLE a (id INTEGER);" },
+            Migration { version: 2, description: "broken", sql: "CREATE TABLE b (id INTEGER); NOT SQL;" },
+        ];
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        assert!(apply(&mut conn, &migrations).is_err());
+
+        assert_eq!(user_version(&conn).unwrap(), 1);
+        assert!(table_exists(&conn, "a"));
+        assert!(!table_exists(&conn, "b"));
+    }
+}
@@ -1,14 +1,13 @@
-use rusqlite::{Connection, Result, params};
-use std::sync::Mutex;
-use lazy_static::lazy_static;
-
-lazy_static! {
-    static ref DB_CONNECTION: Mutex<Connection> = Mutex::new(
-        Connection::open("bot.db").expect("Failed to open database")
-    );
-}
+pub mod migrations;
+mod queries;
+pub mod sessions;
+mod store;
+
+pub use store::{store, SqliteStore, Store, StoreKey};
 
 pub mod language {
+    use tracing::trace;
+
     #[derive(Debug, Clone, Copy)]
     pub enum Language {
         English,
@@ -17,14 +16,14 @@ pub mod language {
 
     impl From<&str> for Language {
         fn from(s: &str) -> Self {
-            println!("🔄 Converting language string: {}", s);
+            trace!("Converting language string: {}", s);
             match s.to_lowercase().as_str() {
                 "japanese" | "ja" | "jp" => {
-                    println!("✅ Detected Japanese language");
+                    trace!("Detected Japanese language");
                     Language::Japanese
                 },
                 _ => {
-                    println!("✅ Defaulting to English language");
+                    trace!("Defaulting to English language");
                     Language::English
                 }
             }
@@ -38,110 +37,126 @@ pub mod language {
     }
 }
 
-use language::Language;
-
-pub fn init_db() -> Result<()> {
-    println!("🔄 Initializing database");
-    let conn = DB_CONNECTION.lock().unwrap();
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS voice_channels (
-            guild_id INTEGER NOT NULL,
-            channel_id INTEGER NOT NULL,
-            is_listening BOOLEAN NOT NULL DEFAULT 0,
-            PRIMARY KEY (guild_id, channel_id)
-        )",
-        [],
-    )?;
-    
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS guild_settings (
-            guild_id INTEGER PRIMARY KEY,
-            language TEXT NOT NULL DEFAULT 'english'
-        )",
-        [],
-    )?;
-    
-    // Reset all listening status on startup
-    conn.execute(
-        "UPDATE voice_channels SET is_listening = 0",
-        [],
-    )?;
-    
-    println!("✅ Database initialized successfully");
-    Ok(())
+pub mod name_announce {
+    /// When the author's name is spoken before their message.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum NameAnnounce {
+        Always,
+        OnChange,
+        Never,
+    }
+
+    impl NameAnnounce {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                NameAnnounce::Always => "always",
+                NameAnnounce::OnChange => "change",
+                NameAnnounce::Never => "never",
+            }
+        }
+    }
+
+    impl From<&str> for NameAnnounce {
+        fn from(s: &str) -> Self {
+            match s.to_lowercase().as_str() {
+                "always" => NameAnnounce::Always,
+                "change" | "onchange" | "on_change" => NameAnnounce::OnChange,
+                _ => NameAnnounce::Never,
+            }
+        }
+    }
+}
+
+/// What a guild ignore list entry refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoreKind {
+    User,
+    Role,
+    Channel,
+}
+
+impl IgnoreKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IgnoreKind::User => "user",
+            IgnoreKind::Role => "role",
+            IgnoreKind::Channel => "channel",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "user" => Some(IgnoreKind::User),
+            "role" => Some(IgnoreKind::Role),
+            "channel" => Some(IgnoreKind::Channel),
+            _ => None,
+        }
+    }
 }
 
-pub fn set_listening_status(guild_id: u64, channel_id: u64, is_listening: bool) -> Result<()> {
-    println!("🔄 Setting listening status for guild {} channel {} to {}", guild_id, channel_id, is_listening);
-    let conn = DB_CONNECTION.lock().unwrap();
-    conn.execute(
-        "INSERT OR REPLACE INTO voice_channels (guild_id, channel_id, is_listening) 
-         VALUES (?1, ?2, ?3)",
-        params![guild_id as i64, channel_id as i64, is_listening as i64],
-    )?;
-    println!("✅ Successfully updated listening status");
-    Ok(())
+/// Which voice channel events are announced in the bot's call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceAnnounceSettings {
+    pub joins: bool,
+    pub leaves: bool,
+    pub moves: bool,
+    pub streams: bool,
+    pub video: bool,
+    /// How long a member's voice state has to settle before it is announced.
+    pub debounce_secs: u64,
 }
 
-pub fn is_listening(guild_id: u64, channel_id: u64) -> Result<bool> {
-    println!("🔍 Checking listening status for guild {} channel {}", guild_id, channel_id);
-    let conn = DB_CONNECTION.lock().unwrap();
-    let mut stmt = conn.prepare(
-        "SELECT is_listening FROM voice_channels 
-         WHERE guild_id = ?1 AND channel_id = ?2"
-    )?;
-    
-    let result: Result<bool> = stmt.query_row(
-        params![guild_id as i64, channel_id as i64],
-        |row| row.get(0)
-    );
-    
-    match result {
-        Ok(is_listening) => {
-            println!("✅ Found listening status: {}", is_listening);
-            Ok(is_listening)
-        },
-        Err(_) => {
-            println!("ℹ️ No listening status found, defaulting to false");
-            Ok(false)
+impl Default for VoiceAnnounceSettings {
+    fn default() -> Self {
+        Self {
+            joins: true,
+            leaves: true,
+            moves: true,
+            streams: false,
+            video: false,
+            debounce_secs: 3,
         }
     }
 }
 
-pub fn set_guild_language(guild_id: u64, language: Language) -> Result<()> {
-    println!("🔄 Setting language for guild {}", guild_id);
-    let conn = DB_CONNECTION.lock().unwrap();
-    let language_str = match language {
-        Language::English => "english",
-        Language::Japanese => "japanese",
-    };
-    conn.execute(
-        "INSERT OR REPLACE INTO guild_settings (guild_id, language) VALUES (?1, ?2)",
-        params![guild_id as i64, language_str],
-    )?;
-    println!("✅ Successfully updated guild language");
-    Ok(())
+/// How a guild's messages are spoken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeechSettings {
+    /// The chosen voice's ID, or `None` for the default voice.
+    pub voice: Option<String>,
+    /// Longer messages are cut off after this many characters.
+    pub max_chars: u32,
 }
 
-pub fn reset_guild_listening_status(guild_id: u64) -> Result<()> {
-    println!("🔄 Resetting all listening statuses for guild {}", guild_id);
-    let conn = DB_CONNECTION.lock().unwrap();
-    conn.execute(
-        "UPDATE voice_channels SET is_listening = 0 WHERE guild_id = ?1",
-        params![guild_id as i64],
-    )?;
-    println!("✅ Successfully reset all listening statuses for guild {}", guild_id);
-    Ok(())
+impl SpeechSettings {
+    pub const MIN_CHARS: u32 = 10;
+    pub const MAX_CHARS: u32 = 500;
 }
 
-pub fn get_guild_language(guild_id: u64) -> Result<Language> {
-    println!("🔍 Getting language for guild {}", guild_id);
-    let conn = DB_CONNECTION.lock().unwrap();
-    let language: String = conn.query_row(
-        "SELECT language FROM guild_settings WHERE guild_id = ?1",
-        params![guild_id as i64],
-        |row| row.get(0),
-    ).unwrap_or_else(|_| "english".to_string());
-    
-    Ok(language.as_str().into())
+impl Default for SpeechSettings {
+    fn default() -> Self {
+        Self { voice: None, max_chars: 120 }
+    }
+}
+
+/// Which parts of a message besides its text are read aloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadSettings {
+    pub replies: bool,
+    pub attachments: bool,
+    pub stickers: bool,
+    pub embeds: bool,
+    pub polls: bool,
+}
+
+impl Default for ReadSettings {
+    fn default() -> Self {
+        Self {
+            replies: true,
+            attachments: true,
+            stickers: true,
+            embeds: true,
+            polls: true,
+        }
+    }
 }
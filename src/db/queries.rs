@@ -0,0 +1,394 @@
+//! The SQL behind [`super::SqliteStore`]. Every query takes the connection it
+//! runs on, so it can be called from the blocking pool.
+
+use rusqlite::{Connection, Result, params};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+use super::language::Language;
+use super::name_announce::NameAnnounce;
+use super::sessions::{SessionRepository, VoiceSession};
+use super::{IgnoreKind, ReadSettings, SpeechSettings, VoiceAnnounceSettings};
+
+pub fn ping(conn: &Connection) -> Result<()> {
+    conn.query_row("SELECT 1", [], |_| Ok(()))
+}
+
+pub fn all_sessions(conn: &Connection) -> Result<Vec<VoiceSession>> {
+    SessionRepository::new(conn).all()
+}
+
+pub fn start_session(conn: &Connection, guild_id: u64, voice_channel_id: u64, started_by: u64) -> Result<()> {
+    debug!("Starting voice session for guild {} in channel {}", guild_id, voice_channel_id);
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    SessionRepository::new(conn).start(guild_id, voice_channel_id, started_by, started_at)?;
+    debug!("Successfully started voice session");
+    Ok(())
+}
+
+pub fn end_session(conn: &Connection, guild_id: u64) -> Result<bool> {
+    debug!("Ending voice session for guild {}", guild_id);
+    SessionRepository::new(conn).end(guild_id)
+}
+
+pub fn get_session(conn: &Connection, guild_id: u64) -> Result<Option<VoiceSession>> {
+    SessionRepository::new(conn).get(guild_id)
+}
+
+pub fn bind_text_channel(conn: &Connection, guild_id: u64, channel_id: u64) -> Result<bool> {
+    debug!("Binding text channel {} to the session of guild {}", channel_id, guild_id);
+    SessionRepository::new(conn).bind_text_channel(guild_id, channel_id)
+}
+
+pub fn unbind_text_channel(conn: &Connection, guild_id: u64, channel_id: u64) -> Result<bool> {
+    debug!("Unbinding text channel {} from the session of guild {}", channel_id, guild_id);
+    SessionRepository::new(conn).unbind_text_channel(guild_id, channel_id)
+}
+
+/// Whether messages in `channel_id` are read aloud in the guild's session.
+pub fn is_bound_text_channel(conn: &Connection, guild_id: u64, channel_id: u64) -> Result<bool> {
+    SessionRepository::new(conn).is_bound(guild_id, channel_id)
+}
+
+pub fn set_guild_language(conn: &Connection, guild_id: u64, language: Language) -> Result<()> {
+    debug!("Setting language for guild {}", guild_id);
+    let language_str = match language {
+        Language::English => "english",
+        Language::Japanese => "japanese",
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO guild_settings (guild_id, language) VALUES (?1, ?2)",
+        params![guild_id as i64, language_str],
+    )?;
+    debug!("Successfully updated guild language");
+    Ok(())
+}
+
+pub fn get_guild_language(conn: &Connection, guild_id: u64) -> Result<Language> {
+    debug!("Getting language for guild {}", guild_id);
+    let language: String = conn.query_row(
+        "SELECT language FROM guild_settings WHERE guild_id = ?1",
+        params![guild_id as i64],
+        |row| row.get(0),
+    ).unwrap_or_else(|_| "english".to_string());
+    
+    Ok(language.as_str().into())
+}
+
+pub fn set_name_announce(conn: &Connection, guild_id: u64, mode: NameAnnounce) -> Result<()> {
+    debug!("Setting name announce mode for guild {} to {}", guild_id, mode.as_str());
+    conn.execute(
+        "INSERT OR REPLACE INTO name_announce_settings (guild_id, mode) VALUES (?1, ?2)",
+        params![guild_id as i64, mode.as_str()],
+    )?;
+    debug!("Successfully updated name announce mode");
+    Ok(())
+}
+
+pub fn get_name_announce(conn: &Connection, guild_id: u64) -> Result<NameAnnounce> {
+    let mode: String = conn.query_row(
+        "SELECT mode FROM name_announce_settings WHERE guild_id = ?1",
+        params![guild_id as i64],
+        |row| row.get(0),
+    ).unwrap_or_else(|_| "never".to_string());
+
+    Ok(mode.as_str().into())
+}
+
+pub fn set_spoken_name(conn: &Connection, guild_id: u64, user_id: u64, reading: &str) -> Result<()> {
+    debug!("Setting spoken name for user {} in guild {}", user_id, guild_id);
+    conn.execute(
+        "INSERT OR REPLACE INTO spoken_names (guild_id, user_id, reading) VALUES (?1, ?2, ?3)",
+        params![guild_id as i64, user_id as i64, reading],
+    )?;
+    debug!("Successfully updated spoken name");
+    Ok(())
+}
+
+pub fn clear_spoken_name(conn: &Connection, guild_id: u64, user_id: u64) -> Result<()> {
+    debug!("Clearing spoken name for user {} in guild {}", user_id, guild_id);
+    conn.execute(
+        "DELETE FROM spoken_names WHERE guild_id = ?1 AND user_id = ?2",
+        params![guild_id as i64, user_id as i64],
+    )?;
+    Ok(())
+}
+
+pub fn get_spoken_name(conn: &Connection, guild_id: u64, user_id: u64) -> Result<Option<String>> {
+    let result = conn.query_row(
+        "SELECT reading FROM spoken_names WHERE guild_id = ?1 AND user_id = ?2",
+        params![guild_id as i64, user_id as i64],
+        |row| row.get(0),
+    );
+
+    match result {
+        Ok(reading) => Ok(Some(reading)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn add_dictionary_entry(conn: &Connection, guild_id: u64, word: &str, reading: &str) -> Result<()> {
+    debug!(
+        "Adding dictionary entry for guild {}: {} -> {}",
+        guild_id,
+        crate::logging::content(word),
+        crate::logging::content(reading)
+    );
+    conn.execute(
+        "INSERT OR REPLACE INTO dictionary (guild_id, word, reading) VALUES (?1, ?2, ?3)",
+        params![guild_id as i64, word, reading],
+    )?;
+    debug!("Successfully added dictionary entry");
+    Ok(())
+}
+
+/// Returns `true` if an entry was removed.
+pub fn remove_dictionary_entry(conn: &Connection, guild_id: u64, word: &str) -> Result<bool> {
+    debug!("Removing dictionary entry for guild {}: {}", guild_id, crate::logging::content(word));
+    let removed = conn.execute(
+        "DELETE FROM dictionary WHERE guild_id = ?1 AND word = ?2",
+        params![guild_id as i64, word],
+    )?;
+    Ok(removed > 0)
+}
+
+pub fn get_dictionary(conn: &Connection, guild_id: u64) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT word, reading FROM dictionary WHERE guild_id = ?1 ORDER BY word"
+    )?;
+    let entries = stmt
+        .query_map(params![guild_id as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, String)>>>()?;
+    Ok(entries)
+}
+
+pub fn set_read_settings(conn: &Connection, guild_id: u64, settings: ReadSettings) -> Result<()> {
+    debug!("Setting read settings for guild {}: {:?}", guild_id, settings);
+    conn.execute(
+        "INSERT OR REPLACE INTO read_settings (guild_id, replies, attachments, stickers, embeds, polls)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            guild_id as i64,
+            settings.replies,
+            settings.attachments,
+            settings.stickers,
+            settings.embeds,
+            settings.polls,
+        ],
+    )?;
+    debug!("Successfully updated read settings");
+    Ok(())
+}
+
+pub fn get_read_settings(conn: &Connection, guild_id: u64) -> Result<ReadSettings> {
+    let result = conn.query_row(
+        "SELECT replies, attachments, stickers, embeds, polls FROM read_settings WHERE guild_id = ?1",
+        params![guild_id as i64],
+        |row| Ok(ReadSettings {
+            replies: row.get(0)?,
+            attachments: row.get(1)?,
+            stickers: row.get(2)?,
+            embeds: row.get(3)?,
+            polls: row.get(4)?,
+        }),
+    );
+
+    match result {
+        Ok(settings) => Ok(settings),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(ReadSettings::default()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn set_voice_announce_settings(conn: &Connection, guild_id: u64, settings: VoiceAnnounceSettings) -> Result<()> {
+    debug!("Setting voice announce settings for guild {}: {:?}", guild_id, settings);
+    conn.execute(
+        "INSERT OR REPLACE INTO voice_announce_settings (guild_id, joins, leaves, moves, streams, video, debounce_secs)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            guild_id as i64,
+            settings.joins,
+            settings.leaves,
+            settings.moves,
+            settings.streams,
+            settings.video,
+            settings.debounce_secs as i64,
+        ],
+    )?;
+    debug!("Successfully updated voice announce settings");
+    Ok(())
+}
+
+pub fn get_voice_announce_settings(conn: &Connection, guild_id: u64) -> Result<VoiceAnnounceSettings> {
+    let result = conn.query_row(
+        "SELECT joins, leaves, moves, streams, video, debounce_secs FROM voice_announce_settings WHERE guild_id = ?1",
+        params![guild_id as i64],
+        |row| Ok(VoiceAnnounceSettings {
+            joins: row.get(0)?,
+            leaves: row.get(1)?,
+            moves: row.get(2)?,
+            streams: row.get(3)?,
+            video: row.get(4)?,
+            debounce_secs: row.get::<_, i64>(5)? as u64,
+        }),
+    );
+
+    match result {
+        Ok(settings) => Ok(settings),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(VoiceAnnounceSettings::default()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn set_speech_settings(conn: &Connection, guild_id: u64, settings: &SpeechSettings) -> Result<()> {
+    debug!("Setting speech settings for guild {}: {:?}", guild_id, settings);
+    conn.execute(
+        "INSERT OR REPLACE INTO speech_settings (guild_id, voice, max_chars) VALUES (?1, ?2, ?3)",
+        params![guild_id as i64, settings.voice, settings.max_chars],
+    )?;
+    debug!("Successfully updated speech settings");
+    Ok(())
+}
+
+pub fn get_speech_settings(conn: &Connection, guild_id: u64) -> Result<SpeechSettings> {
+    let result = conn.query_row(
+        "SELECT voice, max_chars FROM speech_settings WHERE guild_id = ?1",
+        params![guild_id as i64],
+        |row| Ok(SpeechSettings {
+            voice: row.get(0)?,
+            max_chars: row.get(1)?,
+        }),
+    );
+
+    match result {
+        Ok(settings) => Ok(settings),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(SpeechSettings::default()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn set_opted_out(conn: &Connection, user_id: u64, opted_out: bool) -> Result<()> {
+    debug!("Setting TTS opt-out for user {} to {}", user_id, opted_out);
+    if opted_out {
+        conn.execute(
+            "INSERT OR IGNORE INTO tts_optouts (user_id) VALUES (?1)",
+            params![user_id as i64],
+        )?;
+    } else {
+        conn.execute(
+            "DELETE FROM tts_optouts WHERE user_id = ?1",
+            params![user_id as i64],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn is_opted_out(conn: &Connection, user_id: u64) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM tts_optouts WHERE user_id = ?1)",
+        params![user_id as i64],
+        |row| row.get(0),
+    )
+}
+
+/// Adds the entry if it is missing and removes it otherwise. Returns whether
+/// the target is ignored afterwards.
+pub fn toggle_ignore(conn: &Connection, guild_id: u64, kind: IgnoreKind, target_id: u64) -> Result<bool> {
+    debug!("Toggling ignored {} {} in guild {}", kind.as_str(), target_id, guild_id);
+    let removed = conn.execute(
+        "DELETE FROM tts_ignores WHERE guild_id = ?1 AND kind = ?2 AND target_id = ?3",
+        params![guild_id as i64, kind.as_str(), target_id as i64],
+    )?;
+    if removed > 0 {
+        return Ok(false);
+    }
+    conn.execute(
+        "INSERT INTO tts_ignores (guild_id, kind, target_id) VALUES (?1, ?2, ?3)",
+        params![guild_id as i64, kind.as_str(), target_id as i64],
+    )?;
+    Ok(true)
+}
+
+pub fn get_ignores(conn: &Connection, guild_id: u64) -> Result<Vec<(IgnoreKind, u64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT kind, target_id FROM tts_ignores WHERE guild_id = ?1 ORDER BY kind, target_id"
+    )?;
+    let rows = stmt
+        .query_map(params![guild_id as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(kind, target_id)| IgnoreKind::parse(&kind).map(|kind| (kind, target_id as u64)))
+        .collect())
+}
+
+/// Whether a message by `user_id` with `role_ids` in `channel_id` is on the
+/// guild's ignore list.
+pub fn is_ignored(conn: &Connection, guild_id: u64, user_id: u64, role_ids: &[u64], channel_id: u64) -> Result<bool> {
+    Ok(get_ignores(conn, guild_id)?.into_iter().any(|(kind, target_id)| match kind {
+        IgnoreKind::User => target_id == user_id,
+        IgnoreKind::Role => role_ids.contains(&target_id),
+        IgnoreKind::Channel => target_id == channel_id,
+    }))
+}
+
+/// Prefixes that keep a message from being read, `None` if the guild has
+/// never changed them.
+pub fn get_skip_prefixes(conn: &Connection, guild_id: u64) -> Result<Option<Vec<String>>> {
+    let result = conn.query_row(
+        "SELECT prefixes FROM skip_settings WHERE guild_id = ?1",
+        params![guild_id as i64],
+        |row| row.get::<_, String>(0),
+    );
+
+    match result {
+        Ok(prefixes) => Ok(Some(
+            prefixes.lines().filter(|p| !p.is_empty()).map(str::to_string).collect()
+        )),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn set_skip_prefixes(conn: &Connection, guild_id: u64, prefixes: &[String]) -> Result<()> {
+    debug!("Setting skip prefixes for guild {}: {}", guild_id, crate::logging::content(&prefixes.join(" ")));
+    conn.execute(
+        "INSERT OR REPLACE INTO skip_settings (guild_id, prefixes) VALUES (?1, ?2)",
+        params![guild_id as i64, prefixes.join("\n")],
+    )?;
+    debug!("Successfully updated skip prefixes");
+    Ok(())
+}
+
+pub fn get_manager_role(conn: &Connection, guild_id: u64) -> Result<Option<u64>> {
+    let result = conn.query_row(
+        "SELECT role_id FROM manager_roles WHERE guild_id = ?1",
+        params![guild_id as i64],
+        |row| row.get::<_, i64>(0),
+    );
+
+    match result {
+        Ok(role_id) => Ok(Some(role_id as u64)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn set_manager_role(conn: &Connection, guild_id: u64, role_id: Option<u64>) -> Result<()> {
+    debug!("Setting TTS manager role for guild {}: {:?}", guild_id, role_id);
+    match role_id {
+        Some(role_id) => conn.execute(
+            "INSERT OR REPLACE INTO manager_roles (guild_id, role_id) VALUES (?1, ?2)",
+            params![guild_id as i64, role_id as i64],
+        )?,
+        None => conn.execute("DELETE FROM manager_roles WHERE guild_id = ?1", params![guild_id as i64])?,
+    };
+    debug!("Successfully updated TTS manager role");
+    Ok(())
+}
@@ -0,0 +1,292 @@
+//! Voice sessions and the text channels bound to them.
+//!
+//! A guild has at most one session: the voice channel the bot is connected
+//! to, plus any number of text channels whose messages are read aloud there.
+//! The tables are created by migration 3.
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceSession {
+    pub guild_id: u64,
+    pub voice_channel_id: u64,
+    /// Unix timestamp in seconds.
+    pub started_at: i64,
+    pub started_by: u64,
+    pub text_channel_ids: Vec<u64>,
+}
+
+pub struct SessionRepository<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> SessionRepository<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn }
+    }
+
+    /// Starts a session in `voice_channel_id`, replacing any previous session
+    /// of the guild along with its bound text channels.
+    pub fn start(&self, guild_id: u64, voice_channel_id: u64, started_by: u64, started_at: i64) -> Result<()> {
+        // The old session is only gone once the new one is stored
+        let tx = self.conn.unchecked_transaction()?;
+        SessionRepository::new(&tx).end(guild_id)?;
+        tx.execute(
+            "INSERT INTO voice_sessions (guild_id, voice_channel_id, started_at, started_by)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![guild_id as i64, voice_channel_id as i64, started_at, started_by as i64],
+        )?;
+        tx.commit()
+    }
+
+    /// Ends the guild's session. Returns `false` if there was none.
+    pub fn end(&self, guild_id: u64) -> Result<bool> {
+        // Don't rely on the foreign key cascade, it is off unless the connection enables it
+        self.conn.execute(
+            "DELETE FROM session_text_channels WHERE guild_id = ?1",
+            params![guild_id as i64],
+        )?;
+        let removed = self.conn.execute(
+            "DELETE FROM voice_sessions WHERE guild_id = ?1",
+            params![guild_id as i64],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Every persisted session, e.g. to rejoin them after a restart.
+    pub fn all(&self) -> Result<Vec<VoiceSession>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT guild_id, voice_channel_id, started_at, started_by FROM voice_sessions ORDER BY guild_id"
+        )?;
+        let sessions = stmt
+            .query_map([], |row| Ok(VoiceSession {
+                guild_id: row.get::<_, i64>(0)? as u64,
+                voice_channel_id: row.get::<_, i64>(1)? as u64,
+                started_at: row.get(2)?,
+                started_by: row.get::<_, i64>(3)? as u64,
+                text_channel_ids: Vec::new(),
+            }))?
+            .collect::<Result<Vec<_>>>()?;
+
+        sessions
+            .into_iter()
+            .map(|mut session| {
+                session.text_channel_ids = self.text_channels(session.guild_id)?;
+                Ok(session)
+            })
+            .collect()
+    }
+
+    pub fn get(&self, guild_id: u64) -> Result<Option<VoiceSession>> {
+        let session = self.conn.query_row(
+            "SELECT voice_channel_id, started_at, started_by FROM voice_sessions WHERE guild_id = ?1",
+            params![guild_id as i64],
+            |row| Ok(VoiceSession {
+                guild_id,
+                voice_channel_id: row.get::<_, i64>(0)? as u64,
+                started_at: row.get(1)?,
+                started_by: row.get::<_, i64>(2)? as u64,
+                text_channel_ids: Vec::new(),
+            }),
+        ).optional()?;
+
+        match session {
+            Some(mut session) => {
+                session.text_channel_ids = self.text_channels(guild_id)?;
+                Ok(Some(session))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Binds a text channel to the guild's session. Returns `false` if the
+    /// guild has no session to bind to.
+    pub fn bind_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO session_text_channels (guild_id, channel_id)
+             SELECT guild_id, ?2 FROM voice_sessions WHERE guild_id = ?1",
+            params![guild_id as i64, channel_id as i64],
+        )?;
+        Ok(inserted > 0 || self.is_bound(guild_id, channel_id)?)
+    }
+
+    /// Returns `false` if the channel was not bound.
+    pub fn unbind_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool> {
+        let removed = self.conn.execute(
+            "DELETE FROM session_text_channels WHERE guild_id = ?1 AND channel_id = ?2",
+            params![guild_id as i64, channel_id as i64],
+        )?;
+        Ok(removed > 0)
+    }
+
+    pub fn is_bound(&self, guild_id: u64, channel_id: u64) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM session_text_channels WHERE guild_id = ?1 AND channel_id = ?2)",
+            params![guild_id as i64, channel_id as i64],
+            |row| row.get(0),
+        )
+    }
+
+    fn text_channels(&self, guild_id: u64) -> Result<Vec<u64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT channel_id FROM session_text_channels WHERE guild_id = ?1 ORDER BY channel_id"
+        )?;
+        let channel_ids = stmt
+            .query_map(params![guild_id as i64], |row| row.get::<_, i64>(0))?
+            .map(|id| id.map(|id| id as u64))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(channel_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: u64 = 1;
+    const VOICE: u64 = 10;
+    const TEXT: u64 = 20;
+    const USER: u64 = 30;
+
+    fn setup() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::db::migrations::run(&mut conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn start_and_get_session() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+
+        assert_eq!(repo.get(GUILD).unwrap(), None);
+        repo.start(GUILD, VOICE, USER, 1_700_000_000).unwrap();
+
+        assert_eq!(repo.get(GUILD).unwrap(), Some(VoiceSession {
+            guild_id: GUILD,
+            voice_channel_id: VOICE,
+            started_at: 1_700_000_000,
+            started_by: USER,
+            text_channel_ids: vec![],
+        }));
+    }
+
+    #[test]
+    fn several_text_channels_bind_to_one_session() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+        repo.start(GUILD, VOICE, USER, 0).unwrap();
+
+        assert!(repo.bind_text_channel(GUILD, TEXT).unwrap());
+        assert!(repo.bind_text_channel(GUILD, TEXT + 1).unwrap());
+        // Binding twice is fine
+        assert!(repo.bind_text_channel(GUILD, TEXT).unwrap());
+
+        assert_eq!(repo.get(GUILD).unwrap().unwrap().text_channel_ids, vec![TEXT, TEXT + 1]);
+        assert!(repo.is_bound(GUILD, TEXT).unwrap());
+        // The voice channel itself is never a bound text channel
+        assert!(!repo.is_bound(GUILD, VOICE).unwrap());
+    }
+
+    #[test]
+    fn binding_requires_a_session() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+
+        assert!(!repo.bind_text_channel(GUILD, TEXT).unwrap());
+        assert!(!repo.is_bound(GUILD, TEXT).unwrap());
+    }
+
+    #[test]
+    fn unbind_text_channel() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+        repo.start(GUILD, VOICE, USER, 0).unwrap();
+        repo.bind_text_channel(GUILD, TEXT).unwrap();
+
+        assert!(repo.unbind_text_channel(GUILD, TEXT).unwrap());
+        assert!(!repo.unbind_text_channel(GUILD, TEXT).unwrap());
+        assert!(!repo.is_bound(GUILD, TEXT).unwrap());
+        // The session outlives its text channels
+        assert!(repo.get(GUILD).unwrap().is_some());
+    }
+
+    #[test]
+    fn ending_a_session_unbinds_its_text_channels() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+        repo.start(GUILD, VOICE, USER, 0).unwrap();
+        repo.bind_text_channel(GUILD, TEXT).unwrap();
+
+        assert!(repo.end(GUILD).unwrap());
+        assert!(!repo.end(GUILD).unwrap());
+        assert_eq!(repo.get(GUILD).unwrap(), None);
+        assert!(!repo.is_bound(GUILD, TEXT).unwrap());
+    }
+
+    #[test]
+    fn restarting_replaces_the_previous_session() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+        repo.start(GUILD, VOICE, USER, 0).unwrap();
+        repo.bind_text_channel(GUILD, TEXT).unwrap();
+
+        repo.start(GUILD, VOICE + 1, USER + 1, 5).unwrap();
+
+        let session = repo.get(GUILD).unwrap().unwrap();
+        assert_eq!(session.voice_channel_id, VOICE + 1);
+        assert_eq!(session.started_by, USER + 1);
+        assert!(session.text_channel_ids.is_empty());
+    }
+
+    #[test]
+    fn a_failed_restart_keeps_the_previous_session() {
+        let conn = setup();
+        conn.execute_batch(
+            "CREATE TRIGGER refuse_session BEFORE INSERT ON voice_sessions
+             WHEN NEW.voice_channel_id = 99 BEGIN SELECT RAISE(ABORT, 'refused'); END;",
+        )
+        .unwrap();
+        let repo = SessionRepository::new(&conn);
+        repo.start(GUILD, VOICE, USER, 0).unwrap();
+        repo.bind_text_channel(GUILD, TEXT).unwrap();
+
+        assert!(repo.start(GUILD, 99, USER, 5).is_err());
+
+        let session = repo.get(GUILD).unwrap().unwrap();
+        assert_eq!(session.voice_channel_id, VOICE);
+        assert_eq!(session.text_channel_ids, vec![TEXT]);
+    }
+
+    #[test]
+    fn sessions_are_per_guild() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+        repo.start(GUILD, VOICE, USER, 0).unwrap();
+        repo.start(GUILD + 1, VOICE + 1, USER, 0).unwrap();
+        repo.bind_text_channel(GUILD, TEXT).unwrap();
+
+        assert!(!repo.is_bound(GUILD + 1, TEXT).unwrap());
+        assert!(repo.end(GUILD).unwrap());
+        assert!(repo.get(GUILD + 1).unwrap().is_some());
+    }
+
+    #[test]
+    fn all_sessions_survive_with_their_text_channels() {
+        let conn = setup();
+        let repo = SessionRepository::new(&conn);
+        assert!(repo.all().unwrap().is_empty());
+
+        repo.start(GUILD + 1, VOICE + 1, USER, 0).unwrap();
+        repo.start(GUILD, VOICE, USER, 0).unwrap();
+        repo.bind_text_channel(GUILD, TEXT).unwrap();
+        repo.bind_text_channel(GUILD, TEXT + 1).unwrap();
+
+        let sessions = repo.all().unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].guild_id, GUILD);
+        assert_eq!(sessions[0].text_channel_ids, vec![TEXT, TEXT + 1]);
+        assert_eq!(sessions[1].guild_id, GUILD + 1);
+        assert!(sessions[1].text_channel_ids.is_empty());
+    }
+}
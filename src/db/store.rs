@@ -0,0 +1,310 @@
+use rusqlite::{Connection, Result};
+use serenity::async_trait;
+use serenity::prelude::*;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::language::Language;
+use super::migrations::{self, MigrationError};
+use super::name_announce::NameAnnounce;
+use super::queries;
+use super::sessions::VoiceSession;
+use super::{IgnoreKind, ReadSettings, SpeechSettings, VoiceAnnounceSettings};
+
+/// Everything the bot persists. Handlers get it from the client's `TypeMap`
+/// through [`store`] instead of touching SQLite directly.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Runs a trivial query to check that the database is reachable.
+    async fn ping(&self) -> Result<()>;
+
+    async fn start_session(&self, guild_id: u64, voice_channel_id: u64, started_by: u64) -> Result<()>;
+    async fn end_session(&self, guild_id: u64) -> Result<bool>;
+    async fn all_sessions(&self) -> Result<Vec<VoiceSession>>;
+    async fn get_session(&self, guild_id: u64) -> Result<Option<VoiceSession>>;
+    async fn bind_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool>;
+    async fn unbind_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool>;
+    async fn is_bound_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool>;
+
+    async fn set_guild_language(&self, guild_id: u64, language: Language) -> Result<()>;
+    async fn get_guild_language(&self, guild_id: u64) -> Result<Language>;
+    async fn set_name_announce(&self, guild_id: u64, mode: NameAnnounce) -> Result<()>;
+    async fn get_name_announce(&self, guild_id: u64) -> Result<NameAnnounce>;
+    async fn set_read_settings(&self, guild_id: u64, settings: ReadSettings) -> Result<()>;
+    async fn get_read_settings(&self, guild_id: u64) -> Result<ReadSettings>;
+    async fn set_voice_announce_settings(&self, guild_id: u64, settings: VoiceAnnounceSettings) -> Result<()>;
+    async fn get_voice_announce_settings(&self, guild_id: u64) -> Result<VoiceAnnounceSettings>;
+    async fn set_speech_settings(&self, guild_id: u64, settings: SpeechSettings) -> Result<()>;
+    async fn get_speech_settings(&self, guild_id: u64) -> Result<SpeechSettings>;
+
+    async fn set_spoken_name(&self, guild_id: u64, user_id: u64, reading: &str) -> Result<()>;
+    async fn clear_spoken_name(&self, guild_id: u64, user_id: u64) -> Result<()>;
+    async fn get_spoken_name(&self, guild_id: u64, user_id: u64) -> Result<Option<String>>;
+    async fn add_dictionary_entry(&self, guild_id: u64, word: &str, reading: &str) -> Result<()>;
+    async fn remove_dictionary_entry(&self, guild_id: u64, word: &str) -> Result<bool>;
+    async fn get_dictionary(&self, guild_id: u64) -> Result<Vec<(String, String)>>;
+
+    async fn set_opted_out(&self, user_id: u64, opted_out: bool) -> Result<()>;
+    async fn is_opted_out(&self, user_id: u64) -> Result<bool>;
+    async fn toggle_ignore(&self, guild_id: u64, kind: IgnoreKind, target_id: u64) -> Result<bool>;
+    async fn get_ignores(&self, guild_id: u64) -> Result<Vec<(IgnoreKind, u64)>>;
+    async fn is_ignored(&self, guild_id: u64, user_id: u64, role_ids: &[u64], channel_id: u64) -> Result<bool>;
+    async fn get_skip_prefixes(&self, guild_id: u64) -> Result<Option<Vec<String>>>;
+    async fn set_skip_prefixes(&self, guild_id: u64, prefixes: &[String]) -> Result<()>;
+    async fn get_manager_role(&self, guild_id: u64) -> Result<Option<u64>>;
+    async fn set_manager_role(&self, guild_id: u64, role_id: Option<u64>) -> Result<()>;
+}
+
+pub struct StoreKey;
+
+impl TypeMapKey for StoreKey {
+    type Value = Arc<dyn Store>;
+}
+
+/// The store registered in the client's data at startup.
+pub async fn store(ctx: &Context) -> Arc<dyn Store> {
+    ctx.data
+        .read()
+        .await
+        .get::<StoreKey>()
+        .cloned()
+        .expect("Store is registered when the client is built")
+}
+
+/// A [`Store`] backed by a single SQLite connection. Queries run on Tokio's
+/// blocking pool so they never stall the async workers.
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path` and migrates it to the latest schema.
+    pub fn open(path: impl AsRef<Path>) -> std::result::Result<Self, MigrationError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// A private in-memory database, e.g. one per test.
+    #[cfg(test)]
+    pub fn open_in_memory() -> std::result::Result<Self, MigrationError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> std::result::Result<Self, MigrationError> {
+        migrations::run(&mut conn)?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        match tokio::task::spawn_blocking(move || f(&conn.lock().unwrap())).await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn ping(&self) -> Result<()> {
+        self.call(queries::ping).await
+    }
+
+    async fn start_session(&self, guild_id: u64, voice_channel_id: u64, started_by: u64) -> Result<()> {
+        self.call(move |conn| queries::start_session(conn, guild_id, voice_channel_id, started_by)).await
+    }
+
+    async fn end_session(&self, guild_id: u64) -> Result<bool> {
+        self.call(move |conn| queries::end_session(conn, guild_id)).await
+    }
+
+    async fn all_sessions(&self) -> Result<Vec<VoiceSession>> {
+        self.call(queries::all_sessions).await
+    }
+
+    async fn get_session(&self, guild_id: u64) -> Result<Option<VoiceSession>> {
+        self.call(move |conn| queries::get_session(conn, guild_id)).await
+    }
+
+    async fn bind_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool> {
+        self.call(move |conn| queries::bind_text_channel(conn, guild_id, channel_id)).await
+    }
+
+    async fn unbind_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool> {
+        self.call(move |conn| queries::unbind_text_channel(conn, guild_id, channel_id)).await
+    }
+
+    async fn is_bound_text_channel(&self, guild_id: u64, channel_id: u64) -> Result<bool> {
+        self.call(move |conn| queries::is_bound_text_channel(conn, guild_id, channel_id)).await
+    }
+
+    async fn set_guild_language(&self, guild_id: u64, language: Language) -> Result<()> {
+        self.call(move |conn| queries::set_guild_language(conn, guild_id, language)).await
+    }
+
+    async fn get_guild_language(&self, guild_id: u64) -> Result<Language> {
+        self.call(move |conn| queries::get_guild_language(conn, guild_id)).await
+    }
+
+    async fn set_name_announce(&self, guild_id: u64, mode: NameAnnounce) -> Result<()> {
+        self.call(move |conn| queries::set_name_announce(conn, guild_id, mode)).await
+    }
+
+    async fn get_name_announce(&self, guild_id: u64) -> Result<NameAnnounce> {
+        self.call(move |conn| queries::get_name_announce(conn, guild_id)).await
+    }
+
+    async fn set_read_settings(&self, guild_id: u64, settings: ReadSettings) -> Result<()> {
+        self.call(move |conn| queries::set_read_settings(conn, guild_id, settings)).await
+    }
+
+    async fn get_read_settings(&self, guild_id: u64) -> Result<ReadSettings> {
+        self.call(move |conn| queries::get_read_settings(conn, guild_id)).await
+    }
+
+    async fn set_voice_announce_settings(&self, guild_id: u64, settings: VoiceAnnounceSettings) -> Result<()> {
+        self.call(move |conn| queries::set_voice_announce_settings(conn, guild_id, settings)).await
+    }
+
+    async fn get_voice_announce_settings(&self, guild_id: u64) -> Result<VoiceAnnounceSettings> {
+        self.call(move |conn| queries::get_voice_announce_settings(conn, guild_id)).await
+    }
+
+    async fn set_speech_settings(&self, guild_id: u64, settings: SpeechSettings) -> Result<()> {
+        self.call(move |conn| queries::set_speech_settings(conn, guild_id, &settings)).await
+    }
+
+    async fn get_speech_settings(&self, guild_id: u64) -> Result<SpeechSettings> {
+        self.call(move |conn| queries::get_speech_settings(conn, guild_id)).await
+    }
+
+    async fn set_spoken_name(&self, guild_id: u64, user_id: u64, reading: &str) -> Result<()> {
+        let reading = reading.to_string();
+        self.call(move |conn| queries::set_spoken_name(conn, guild_id, user_id, &reading)).await
+    }
+
+    async fn clear_spoken_name(&self, guild_id: u64, user_id: u64) -> Result<()> {
+        self.call(move |conn| queries::clear_spoken_name(conn, guild_id, user_id)).await
+    }
+
+    async fn get_spoken_name(&self, guild_id: u64, user_id: u64) -> Result<Option<String>> {
+        self.call(move |conn| queries::get_spoken_name(conn, guild_id, user_id)).await
+    }
+
+    async fn add_dictionary_entry(&self, guild_id: u64, word: &str, reading: &str) -> Result<()> {
+        let (word, reading) = (word.to_string(), reading.to_string());
+        self.call(move |conn| queries::add_dictionary_entry(conn, guild_id, &word, &reading)).await
+    }
+
+    async fn remove_dictionary_entry(&self, guild_id: u64, word: &str) -> Result<bool> {
+        let word = word.to_string();
+        self.call(move |conn| queries::remove_dictionary_entry(conn, guild_id, &word)).await
+    }
+
+    async fn get_dictionary(&self, guild_id: u64) -> Result<Vec<(String, String)>> {
+        self.call(move |conn| queries::get_dictionary(conn, guild_id)).await
+    }
+
+    async fn set_opted_out(&self, user_id: u64, opted_out: bool) -> Result<()> {
+        self.call(move |conn| queries::set_opted_out(conn, user_id, opted_out)).await
+    }
+
+    async fn is_opted_out(&self, user_id: u64) -> Result<bool> {
+        self.call(move |conn| queries::is_opted_out(conn, user_id)).await
+    }
+
+    async fn toggle_ignore(&self, guild_id: u64, kind: IgnoreKind, target_id: u64) -> Result<bool> {
+        self.call(move |conn| queries::toggle_ignore(conn, guild_id, kind, target_id)).await
+    }
+
+    async fn get_ignores(&self, guild_id: u64) -> Result<Vec<(IgnoreKind, u64)>> {
+        self.call(move |conn| queries::get_ignores(conn, guild_id)).await
+    }
+
+    async fn is_ignored(&self, guild_id: u64, user_id: u64, role_ids: &[u64], channel_id: u64) -> Result<bool> {
+        let role_ids = role_ids.to_vec();
+        self.call(move |conn| queries::is_ignored(conn, guild_id, user_id, &role_ids, channel_id)).await
+    }
+
+    async fn get_skip_prefixes(&self, guild_id: u64) -> Result<Option<Vec<String>>> {
+        self.call(move |conn| queries::get_skip_prefixes(conn, guild_id)).await
+    }
+
+    async fn set_skip_prefixes(&self, guild_id: u64, prefixes: &[String]) -> Result<()> {
+        let prefixes = prefixes.to_vec();
+        self.call(move |conn| queries::set_skip_prefixes(conn, guild_id, &prefixes)).await
+    }
+
+    async fn get_manager_role(&self, guild_id: u64) -> Result<Option<u64>> {
+        self.call(move |conn| queries::get_manager_role(conn, guild_id)).await
+    }
+
+    async fn set_manager_role(&self, guild_id: u64, role_id: Option<u64>) -> Result<()> {
+        self.call(move |conn| queries::set_manager_role(conn, guild_id, role_id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn settings_default_until_set() {
+        let store = SqliteStore::open_in_memory().unwrap();
+
+        assert_eq!(store.get_read_settings(1).await.unwrap(), ReadSettings::default());
+        assert_eq!(store.get_name_announce(1).await.unwrap(), NameAnnounce::Never);
+        assert_eq!(store.get_skip_prefixes(1).await.unwrap(), None);
+        assert_eq!(store.get_speech_settings(1).await.unwrap(), SpeechSettings::default());
+        assert_eq!(store.get_manager_role(1).await.unwrap(), None);
+
+        let settings = ReadSettings { polls: false, ..ReadSettings::default() };
+        store.set_read_settings(1, settings).await.unwrap();
+        store.set_skip_prefixes(1, &[]).await.unwrap();
+        let speech = SpeechSettings { voice: Some("calm".to_string()), max_chars: 40 };
+        store.set_speech_settings(1, speech.clone()).await.unwrap();
+        store.set_manager_role(1, Some(5)).await.unwrap();
+
+        assert_eq!(store.get_read_settings(1).await.unwrap(), settings);
+        assert_eq!(store.get_skip_prefixes(1).await.unwrap(), Some(vec![]));
+        assert_eq!(store.get_speech_settings(1).await.unwrap(), speech);
+        assert_eq!(store.get_manager_role(1).await.unwrap(), Some(5));
+        store.set_manager_role(1, None).await.unwrap();
+        assert_eq!(store.get_manager_role(1).await.unwrap(), None);
+        // Other guilds are unaffected
+        assert_eq!(store.get_read_settings(2).await.unwrap(), ReadSettings::default());
+    }
+
+    #[tokio::test]
+    async fn ignore_list_matches_users_roles_and_channels() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        store.toggle_ignore(1, IgnoreKind::User, 10).await.unwrap();
+        store.toggle_ignore(1, IgnoreKind::Role, 20).await.unwrap();
+        store.toggle_ignore(1, IgnoreKind::Channel, 30).await.unwrap();
+
+        assert!(store.is_ignored(1, 10, &[], 0).await.unwrap());
+        assert!(store.is_ignored(1, 0, &[5, 20], 0).await.unwrap());
+        assert!(store.is_ignored(1, 0, &[], 30).await.unwrap());
+        assert!(!store.is_ignored(1, 0, &[5], 0).await.unwrap());
+        assert!(!store.is_ignored(2, 10, &[20], 30).await.unwrap());
+
+        // Toggling again removes the entry
+        assert!(!store.toggle_ignore(1, IgnoreKind::User, 10).await.unwrap());
+        assert!(!store.is_ignored(1, 10, &[], 0).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn dictionary_round_trip() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        store.add_dictionary_entry(1, "w", "わら").await.unwrap();
+        store.add_dictionary_entry(1, "w", "笑").await.unwrap();
+
+        assert_eq!(store.get_dictionary(1).await.unwrap(), vec![("w".to_string(), "笑".to_string())]);
+        assert!(store.remove_dictionary_entry(1, "w").await.unwrap());
+        assert!(!store.remove_dictionary_entry(1, "w").await.unwrap());
+    }
+}
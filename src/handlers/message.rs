@@ -1,116 +1,154 @@
 use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
 use serenity::prelude::*;
-use songbird::input::Input;
-use regex::Regex;
-use lazy_static::lazy_static;
-use serenity::model::id::UserId;
+use std::fmt;
+use tracing::{debug, error, warn};
 
-use crate::api::hiroyuki;
 use crate::db;
-
-lazy_static! {
-    static ref URL_REGEX: Regex = Regex::new(r"https?://[^\s]+").unwrap();
-    static ref MENTION_REGEX: Regex = Regex::new(r"<@!?(\d+)>").unwrap();
-}
+use crate::tts;
 
 pub async fn handle_message(ctx: &Context, msg: &Message) -> Result<(), String> {
     // Ignore messages from bots to prevent potential loops
     if msg.author.bot {
-        println!("🤖 Ignoring bot message");
+        debug!("Ignoring bot message");
         return Ok(());
     }
 
     let guild_id = msg.guild_id.ok_or("Not in a guild")?;
     let channel_id = msg.channel_id;
 
-    println!("📝 Message received in guild {} channel {}", guild_id, channel_id);
+    debug!(content = %crate::logging::content(&msg.content), "Message received");
 
-    // Check if bot should be listening in this channel
-    match db::is_listening(guild_id.get(), channel_id.get()) {
-        Ok(is_listening) => {
-            println!("🎧 Listening status for channel {}: {}", channel_id, is_listening);
-            if !is_listening {
-                println!("❌ Not listening in this channel, ignoring message");
+    let store = db::store(ctx).await;
+
+    // Check if this channel is bound to the guild's voice session
+    match store.is_bound_text_channel(guild_id.get(), channel_id.get()).await {
+        Ok(is_bound) => {
+            debug!(is_bound, "Checked channel binding");
+            if !is_bound {
+                debug!("Not reading this channel, ignoring message");
                 return Ok(());
             }
 
             // Get the voice manager to check if we're actually in a voice channel
-            println!("🎤 Getting voice manager");
+            debug!("Getting voice manager");
             let manager = match songbird::get(ctx).await {
                 Some(manager) => manager.clone(),
                 None => {
-                    println!("❌ Voice client not available");
+                    error!("Voice client not available");
                     return Err("Failed to get voice client".to_string());
                 }
             };
 
-            // If we're supposed to be listening but not in a voice channel, update the database
-            if !manager.get(guild_id).is_some() {
-                println!("⚠️ Database says listening but not in voice channel, updating status");
-                if let Err(e) = db::set_listening_status(guild_id.get(), channel_id.get(), false) {
-                    println!("❌ Failed to update listening status: {}", e);
-                }
+            // The session may not have been rejoined yet after a restart
+            if manager.get(guild_id).is_none() {
+                warn!("Database has a session but not in voice channel, ignoring message");
                 return Ok(());
             }
         },
         Err(e) => {
-            println!("❌ Error checking listening status: {}", e);
-            return Err(format!("Failed to check listening status: {}", e));
+            error!("Error checking session binding: {}", e);
+            return Err(format!("Failed to check session binding: {}", e));
         }
     }
 
-    println!("🎤 Getting voice manager for playback");
-    // Get the voice manager
-    let manager = songbird::get(ctx)
-        .await
-        .ok_or("Failed to get voice client")?
-        .clone();
-
-    // Check if bot is connected to a voice channel in this guild
-    let handler_lock = match manager.get(guild_id) {
-        Some(handler) => {
-            println!("✅ Found voice handler for guild {}", guild_id);
-            handler
-        },
-        None => {
-            println!("❌ Bot is not in a voice channel");
-            return Ok(());  // Bot is not in a voice channel, ignore message
+    read(ctx, guild_id, msg).await.map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// What [`read`] did with a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Queued,
+    OptedOut,
+    Ignored,
+    /// Starts with a skip prefix or is a command for another bot.
+    Skipped,
+    /// Nothing is left to read once filtered.
+    Empty,
+    /// The bot isn't in a call, or is shutting down.
+    NotConnected,
+}
+
+/// Why [`read`] failed.
+#[derive(Debug)]
+pub enum ReadError {
+    /// Checking the opt-outs or the ignore list failed.
+    Database(rusqlite::Error),
+    /// Synthesizing or queueing the message failed.
+    Speech(String),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Database(e) => write!(f, "Failed to check whether to read the message: {}", e),
+            ReadError::Speech(e) => write!(f, "{}", e),
         }
-    };
-
-    // Process message content
-    let mut processed_content = msg.content.clone();
-
-    // Replace user mentions with usernames using regex
-    if let Some(guild) = msg.guild(&ctx.cache) {
-        processed_content = MENTION_REGEX.replace_all(&processed_content, |caps: &regex::Captures| {
-            if let Ok(user_id) = caps[1].parse::<u64>() {
-                let user_id = UserId::new(user_id);
-                if let Some(member) = guild.members.get(&user_id) {
-                    return member.display_name().to_string();
-                }
-            }
-            caps[0].to_string()
-        }).to_string();
     }
+}
 
-    // Replace URLs with リンク省略
-    processed_content = URL_REGEX.replace_all(&processed_content, "リンク省略").to_string();
-    println!("🔊 Generating voice for message: {}", processed_content);
-    
-    // Get audio data from Hiroyuki API
-    let audio_data = hiroyuki::get_hiroyuki_voice(&processed_content)
-        .await
-        .map_err(|e| format!("Failed to get Hiroyuki voice: {}", e))?;
-
-    // Create input from the audio bytes
-    let input = Input::try_from(audio_data)
-        .map_err(|e| format!("Failed to create input source: {}", e))?;
-
-    // Play the audio
-    let mut handler = handler_lock.lock().await;
-    println!("🎵 Playing audio");
-    handler.play_input(input);
-
-    Ok(())
+/// Fills in the author's member on messages fetched over REST or resolved
+/// from an interaction, which come without one, so role ignores and
+/// nicknames apply to them too.
+pub async fn with_member(ctx: &Context, guild_id: GuildId, msg: &Message) -> Message {
+    let mut msg = msg.clone();
+    if msg.member.is_none() {
+        match guild_id.member(ctx, msg.author.id).await {
+            Ok(member) => msg.member = Some(Box::new(member.into())),
+            // The author may have left the server
+            Err(e) => debug!("Failed to get the author's member: {}", e),
+        }
+    }
+    msg
+}
+
+/// Reads `msg` aloud in the guild's call, unless its author opted out, it
+/// matches the ignore list or a skip prefix, or there is nothing to read.
+/// Shared with the "Read aloud" context menu and 🔊 reactions.
+pub async fn read(ctx: &Context, guild_id: GuildId, msg: &Message) -> Result<Outcome, ReadError> {
+    let store = db::store(ctx).await;
+    let channel_id = msg.channel_id;
+
+    // Respect opt-outs and the guild's ignore list before doing any synthesis work
+    if store.is_opted_out(msg.author.id.get()).await.map_err(ReadError::Database)? {
+        debug!("Author has opted out of TTS, ignoring message");
+        return Ok(Outcome::OptedOut);
+    }
+    let role_ids = msg.member
+        .as_ref()
+        .map(|member| member.roles.iter().map(|role| role.get()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if store.is_ignored(guild_id.get(), msg.author.id.get(), &role_ids, channel_id.get()).await
+        .map_err(ReadError::Database)?
+    {
+        debug!("Message matches the guild's ignore list, ignoring message");
+        return Ok(Outcome::Ignored);
+    }
+
+    // Skip prefixes and commands for other bots are checked before normalization
+    if tts::skip::should_skip(ctx, guild_id, &msg.content).await {
+        debug!("Message starts with a skip prefix, ignoring message");
+        return Ok(Outcome::Skipped);
+    }
+
+    // Process message content along with replies, attachments, stickers, embeds and polls
+    let processed_content = tts::context::compose(ctx, guild_id, msg).await;
+    if processed_content.trim().is_empty() {
+        debug!("Nothing to read in this message");
+        return Ok(Outcome::Empty);
+    }
+    let processed_content = tts::names::with_author_name(ctx, guild_id, msg, processed_content).await;
+
+    match tts::speak(ctx, guild_id, &processed_content).await {
+        Ok(Some(track)) => {
+            crate::metrics::MESSAGES_READ.inc();
+            tts::indicator::attach(ctx.http.clone(), &track, msg.channel_id, msg.id);
+            Ok(Outcome::Queued)
+        }
+        Ok(None) => Ok(Outcome::NotConnected),
+        Err(e) => {
+            tts::indicator::mark_failed(&ctx.http, msg.channel_id, msg.id).await;
+            Err(ReadError::Speech(e))
+        }
+    }
 }
@@ -1,2 +1,4 @@
 pub mod message;
+pub mod reaction;
+pub mod restore;
 pub mod voice_state;
@@ -0,0 +1,117 @@
+use lazy_static::lazy_static;
+use serenity::model::channel::{Message, Reaction};
+use serenity::prelude::*;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::commands::readaloud::ReadAloud;
+use crate::commands::registry::{Bucket, Cooldowns};
+use crate::commands::SlashCommand;
+use crate::db;
+use crate::handlers::message;
+use crate::tts::indicator;
+
+lazy_static! {
+    /// When each user may ask for a message to be read again.
+    static ref COOLDOWNS: Mutex<Cooldowns> = Mutex::new(Cooldowns::default());
+}
+
+/// Whether the bot's own 🔊 is on `msg`, i.e. it is playing right now.
+fn is_playing(msg: &Message) -> bool {
+    msg.reactions.iter().any(|reaction| reaction.me && indicator::is_speaking(&reaction.reaction_type))
+}
+
+/// Reads a message again when someone reacts to it with 🔊 in a channel
+/// that is being read.
+pub async fn handle_reaction_add(ctx: &Context, reaction: &Reaction) -> Result<(), String> {
+    if !indicator::is_speaking(&reaction.emoji) {
+        return Ok(());
+    }
+    let Some(guild_id) = reaction.guild_id else {
+        return Ok(());
+    };
+
+    // The bot's own 🔊 marks the message that is playing
+    let user_id = reaction.user_id.ok_or("Reaction has no user")?;
+    if user_id == ctx.cache.current_user().id {
+        return Ok(());
+    }
+    if reaction.member.as_ref().is_some_and(|member| member.user.bot) {
+        debug!("Ignoring reaction from a bot");
+        return Ok(());
+    }
+
+    let store = db::store(ctx).await;
+    let is_bound = store
+        .is_bound_text_channel(guild_id.get(), reaction.channel_id.get())
+        .await
+        .map_err(|e| format!("Failed to check session binding: {}", e))?;
+    if !is_bound {
+        debug!("Not reading this channel, ignoring reaction");
+        return Ok(());
+    }
+    let manager = songbird::get(ctx).await.ok_or("Failed to get voice client")?;
+    if manager.get(guild_id).is_none() {
+        debug!("Bot is not in a voice channel");
+        return Ok(());
+    }
+
+    // Re-reading costs as much as "Read aloud", so it's limited the same way
+    if COOLDOWNS.lock().await.remaining("reaction", Bucket::User(user_id), Instant::now()).is_some() {
+        debug!("User is on cooldown, ignoring reaction");
+        return Ok(());
+    }
+
+    let msg = reaction
+        .message(&ctx.http)
+        .await
+        .map_err(|e| format!("Failed to fetch reacted message: {}", e))?;
+    if is_playing(&msg) {
+        debug!("Message is already playing, ignoring reaction");
+        return Ok(());
+    }
+    let msg = message::with_member(ctx, guild_id, &msg).await;
+
+    // The cooldown only starts once the message is actually read; checked
+    // again since another reaction may have started it during the fetch
+    {
+        let now = Instant::now();
+        let mut cooldowns = COOLDOWNS.lock().await;
+        if cooldowns.remaining("reaction", Bucket::User(user_id), now).is_some() {
+            debug!("User is on cooldown, ignoring reaction");
+            return Ok(());
+        }
+        let cooldown = ReadAloud.cooldown().unwrap_or(Duration::ZERO);
+        cooldowns.start("reaction", Bucket::User(user_id), cooldown, now);
+    }
+    debug!(message_id = msg.id.get(), "Reading message again");
+    message::read(ctx, guild_id, &msg).await.map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn reaction(emoji: &str, me: bool) -> serenity::model::channel::MessageReaction {
+        serde_json::from_value(json!({
+            "count": 1,
+            "count_details": { "burst": 0, "normal": 1 },
+            "me": me,
+            "me_burst": false,
+            "emoji": { "id": null, "name": emoji },
+            "burst_colors": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn only_the_bots_speaker_marks_a_playing_message() {
+        let mut msg = Message::default();
+        assert!(!is_playing(&msg));
+        msg.reactions = vec![reaction(indicator::SPEAKING, false), reaction(indicator::FAILED, true)];
+        assert!(!is_playing(&msg));
+        msg.reactions.push(reaction(indicator::SPEAKING, true));
+        assert!(is_playing(&msg));
+    }
+}
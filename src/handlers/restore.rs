@@ -0,0 +1,228 @@
+//! Rejoins the voice sessions that were active when the bot last shut down.
+//!
+//! Each shard only knows about its own guilds, so sessions are handled per
+//! shard: its `ready` event ends the sessions of guilds the bot was removed
+//! from, and each guild's session is restored when the guild becomes
+//! available on its shard.
+
+use lazy_static::lazy_static;
+use serenity::model::gateway::Ready;
+use serenity::model::guild::Guild;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::prelude::*;
+use std::collections::HashSet;
+use tracing::{debug, error, info};
+
+use crate::db::language::Language;
+use crate::db::sessions::VoiceSession;
+use crate::db::{self, Store};
+
+lazy_static! {
+    /// Guilds whose session was already looked at. Guilds are announced again
+    /// whenever a shard has to re-identify, but only need restoring once per
+    /// process.
+    static ref RESTORED: Mutex<HashSet<GuildId>> = Mutex::new(HashSet::new());
+}
+
+/// Ends the sessions of guilds on the ready shard that the bot is no longer in.
+pub async fn end_removed_guild_sessions(ctx: &Context, ready: &Ready) {
+    let (shard_id, total) = ready.shard.map(|shard| (shard.id.0, shard.total)).unwrap_or((0, 1));
+    let guilds = ready.guilds.iter().map(|guild| guild.id).collect::<HashSet<_>>();
+
+    let store = db::store(ctx).await;
+    let sessions = match store.all_sessions().await {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            error!("Failed to load voice sessions: {}", e);
+            return;
+        }
+    };
+
+    for session in sessions {
+        let guild_id = GuildId::new(session.guild_id);
+        if serenity::utils::shard_id(guild_id, total) == shard_id && !guilds.contains(&guild_id) {
+            info!(guild_id = %guild_id, shard_id, "Guild is gone, ending its session");
+            end_session(store.as_ref(), guild_id).await;
+        }
+    }
+}
+
+/// Rejoins the guild's session once it is available on its shard.
+///
+/// Sessions whose voice channel was deleted or has no listeners left are
+/// ended instead, and bound text channels that no longer exist are unbound.
+pub async fn restore_guild(ctx: &Context, guild_id: GuildId) {
+    if !RESTORED.lock().await.insert(guild_id) {
+        return;
+    }
+
+    let store = db::store(ctx).await;
+    let session = match store.get_session(guild_id.get()).await {
+        Ok(Some(session)) => session,
+        Ok(None) => return,
+        Err(e) => {
+            error!(guild_id = %guild_id, "Failed to load voice session: {}", e);
+            return;
+        }
+    };
+
+    info!(guild_id = %guild_id, shard_id = ctx.shard_id.0, "Restoring voice session");
+    if let Err(e) = restore_session(ctx, store.as_ref(), &session).await {
+        error!(guild_id = %guild_id, "Failed to restore session: {}", e);
+        end_session(store.as_ref(), guild_id).await;
+    }
+}
+
+async fn restore_session(ctx: &Context, store: &dyn Store, session: &VoiceSession) -> Result<(), String> {
+    let guild_id = GuildId::new(session.guild_id);
+    let voice_channel_id = ChannelId::new(session.voice_channel_id);
+
+    // Check the channels against the cache before any async calls
+    let (listeners, stale_text_channels) = {
+        let guild = guild_id
+            .to_guild_cached(&ctx.cache)
+            .ok_or("Failed to get guild")?;
+
+        if !guild.channels.contains_key(&voice_channel_id) {
+            return Err("Voice channel no longer exists".to_string());
+        }
+
+        let listeners = count_listeners(&guild, voice_channel_id, ctx.cache.current_user().id);
+
+        let stale_text_channels = session.text_channel_ids
+            .iter()
+            .map(|id| ChannelId::new(*id))
+            .filter(|id| !guild.channels.contains_key(id) && !guild.threads.iter().any(|thread| thread.id == *id))
+            .collect::<Vec<_>>();
+
+        (listeners, stale_text_channels)
+    }; // guild is dropped here
+
+    if listeners == 0 {
+        return Err("No non-bot users left in the voice channel".to_string());
+    }
+
+    for channel_id in &stale_text_channels {
+        info!(guild_id = %guild_id, channel_id = %channel_id, "Text channel is gone, unbinding it");
+        if let Err(e) = store.unbind_text_channel(guild_id.get(), channel_id.get()).await {
+            error!("Failed to unbind text channel: {}", e);
+        }
+    }
+
+    let manager = songbird::get(ctx).await.ok_or("Failed to get voice client")?.clone();
+    if let Some(handler_lock) = manager.get(guild_id) {
+        if handler_lock.lock().await.current_channel().is_some() {
+            debug!(guild_id = %guild_id, "Already in a voice channel, not restoring");
+            return Ok(());
+        }
+    }
+
+    let handler_lock = manager
+        .join(guild_id, voice_channel_id)
+        .await
+        .map_err(|e| format!("Failed to join voice channel: {}", e))?;
+    {
+        let mut handler = handler_lock.lock().await;
+        if let Err(e) = handler.deafen(true).await {
+            // Don't stay in the call without a session to read into it
+            let _ = handler.leave().await;
+            return Err(format!("Failed to deafen: {}", e));
+        }
+    }
+    info!(guild_id = %guild_id, channel_id = %voice_channel_id, "Rejoined voice channel");
+
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+    let notice = match language {
+        Language::English => "🔄 Reconnected after a restart, reading this channel again!",
+        Language::Japanese => "🔄 再起動後に再接続しました。このチャンネルの読み上げを再開します！",
+    };
+    for channel_id in session.text_channel_ids.iter().map(|id| ChannelId::new(*id)) {
+        if stale_text_channels.contains(&channel_id) {
+            continue;
+        }
+        if let Err(e) = channel_id.say(&ctx.http, notice).await {
+            error!(guild_id = %guild_id, channel_id = %channel_id, "Failed to post reconnect notice: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// The users in `channel_id` other than the bot itself.
+///
+/// The voice states in GUILD_CREATE come without their member, so bots are
+/// only left out when their member is cached.
+pub fn count_listeners(guild: &Guild, channel_id: ChannelId, bot_id: UserId) -> usize {
+    guild
+        .voice_states
+        .values()
+        .filter(|state| state.channel_id == Some(channel_id) && state.user_id != bot_id)
+        .filter(|state| !guild.members.get(&state.user_id).is_some_and(|member| member.user.bot))
+        .count()
+}
+
+async fn end_session(store: &dyn Store, guild_id: GuildId) {
+    if let Err(e) = store.end_session(guild_id.get()).await {
+        error!("Failed to end voice session: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const VOICE: u64 = 10;
+
+    fn voice_state(user_id: u64, channel_id: u64) -> serenity::model::voice::VoiceState {
+        // Like the voice states in GUILD_CREATE, without a member
+        serde_json::from_value(json!({
+            "channel_id": channel_id.to_string(),
+            "user_id": user_id.to_string(),
+            "session_id": "session",
+            "deaf": false,
+            "mute": false,
+            "self_deaf": false,
+            "self_mute": false,
+            "self_video": false,
+            "suppress": false,
+            "request_to_speak_timestamp": null,
+        }))
+        .unwrap()
+    }
+
+    fn member(user_id: u64, bot: bool) -> serenity::model::guild::Member {
+        serde_json::from_value(json!({
+            "user": {
+                "id": user_id.to_string(),
+                "username": "user",
+                "discriminator": "0",
+                "avatar": null,
+                "bot": bot,
+            },
+            "roles": [],
+            "joined_at": "2024-01-01T00:00:00Z",
+            "deaf": false,
+            "mute": false,
+            "flags": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn listeners_are_counted_without_members() {
+        let bot_id = UserId::new(1);
+        let mut guild = Guild::default();
+        for (user_id, channel_id) in [(1, VOICE), (2, VOICE), (3, VOICE), (4, 11)] {
+            guild.voice_states.insert(UserId::new(user_id), voice_state(user_id, channel_id));
+        }
+        assert!(guild.voice_states.values().all(|state| state.member.is_none()));
+        assert_eq!(count_listeners(&guild, ChannelId::new(VOICE), bot_id), 2);
+
+        // Another bot is left out once its member is cached
+        guild.members.insert(UserId::new(3), member(3, true));
+        guild.members.insert(UserId::new(2), member(2, false));
+        assert_eq!(count_listeners(&guild, ChannelId::new(VOICE), bot_id), 1);
+        assert_eq!(count_listeners(&guild, ChannelId::new(12), bot_id), 0);
+    }
+}
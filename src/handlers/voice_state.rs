@@ -1,8 +1,16 @@
 use serenity::model::voice::VoiceState;
 use serenity::prelude::*;
 use songbird::get;
+use tracing::{error, info};
+
+use crate::handlers::restore::count_listeners;
 
 pub async fn handle_voice_state_update(ctx: &Context, old: Option<VoiceState>, new: VoiceState) -> Result<(), String> {
+    // Announce joins, leaves, moves, streams and cameras in the bot's channel
+    if let Some(guild_id) = new.guild_id {
+        crate::tts::announce::schedule(ctx, guild_id, old.as_ref(), &new);
+    }
+
     // If user joined a channel, we don't need to do anything
     if new.channel_id.is_some() {
         return Ok(());
@@ -15,49 +23,50 @@ pub async fn handle_voice_state_update(ctx: &Context, old: Option<VoiceState>, n
 
             // Check if the bot was the one who left/was kicked
             if new.user_id == ctx.cache.current_user().id {
-                println!("🚫 Bot was disconnected from voice channel in guild {}", guild_id);
-                // Reset all listening statuses for this guild
-                if let Err(e) = crate::db::reset_guild_listening_status(guild_id.get()) {
-                    println!("❌ Failed to reset listening statuses: {}", e);
+                info!("Bot was disconnected from voice channel");
+                // Leaving on shutdown keeps the session so it is restored on the next start
+                if crate::shutdown::is_shutting_down() {
+                    return Ok(());
                 }
+                let store = crate::db::store(ctx).await;
+                // End the session, unless it already moved on to another channel
+                match store.get_session(guild_id.get()).await {
+                    Ok(Some(session)) if session.voice_channel_id == old_channel_id.get() => {
+                        if let Err(e) = store.end_session(guild_id.get()).await {
+                            error!("Failed to end voice session: {}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to get voice session: {}", e),
+                }
+                crate::tts::names::forget_guild(guild_id);
                 return Ok(());
             }
 
-            // Get the guild and count non-bot users before any async calls
+            // Count the listeners left before any async calls. Voice states
+            // restored from GUILD_CREATE have no member, so this goes through
+            // the same member cache lookup as the session restore.
             let non_bot_count = {
                 let guild = guild_id
                     .to_guild_cached(&ctx.cache)
                     .ok_or("Failed to get guild")?;
 
-                guild.voice_states
-                    .values()
-                    .filter(|state| {
-                        // Check if user is in this channel and is not a bot
-                        if let Some(channel_id) = state.channel_id {
-                            if channel_id == old_channel_id {
-                                if let Some(member) = state.member.as_ref() {
-                                    return !member.user.bot;
-                                }
-                            }
-                        }
-                        false
-                    })
-                    .count()
+                count_listeners(&guild, old_channel_id, ctx.cache.current_user().id)
             }; // guild is dropped here
 
             // If no non-bot users are left in the channel
             if non_bot_count == 0 {
-                println!("🚪 No non-bot users left in channel {}, disconnecting", old_channel_id);
+                info!(channel_id = %old_channel_id, "No non-bot users left in channel, disconnecting");
                 
                 // Get the voice manager
                 if let Some(manager) = get(ctx).await {
                     if let Some(handler_lock) = manager.get(guild_id) {
                         let mut handler = handler_lock.lock().await;
                         if let Err(e) = handler.leave().await {
-                            println!("❌ Error leaving voice channel: {}", e);
+                            error!("Error leaving voice channel: {}", e);
                             return Err(format!("Failed to leave voice channel: {}", e));
                         }
-                        println!("✅ Successfully left empty voice channel");
+                        info!("Successfully left empty voice channel");
                     }
                 }
             }
@@ -66,3 +75,39 @@ pub async fn handle_voice_state_update(ctx: &Context, old: Option<VoiceState>, n
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use serenity::model::guild::Guild;
+    use serenity::model::id::{ChannelId, UserId};
+
+    #[test]
+    fn restored_listeners_keep_the_bot_in_the_channel() {
+        let bot_id = UserId::new(1);
+        let mut guild = Guild::default();
+        for user_id in [1, 2] {
+            // Like the voice states in GUILD_CREATE, without a member
+            let state: VoiceState = serde_json::from_value(json!({
+                "channel_id": "10",
+                "user_id": user_id.to_string(),
+                "session_id": "session",
+                "deaf": false,
+                "mute": false,
+                "self_deaf": false,
+                "self_mute": false,
+                "self_video": false,
+                "suppress": false,
+                "request_to_speak_timestamp": null,
+            }))
+            .unwrap();
+            guild.voice_states.insert(UserId::new(user_id), state);
+        }
+
+        // Someone else left; the restored listener without a member still counts
+        assert_eq!(count_listeners(&guild, ChannelId::new(10), bot_id), 1);
+        guild.voice_states.remove(&UserId::new(2));
+        assert_eq!(count_listeners(&guild, ChannelId::new(10), bot_id), 0);
+    }
+}
@@ -0,0 +1,172 @@
+//! A small HTTP server for operational endpoints:
+//!
+//! - `/metrics`: Prometheus metrics
+//! - `/healthz`: the process is alive and serving requests
+//! - `/readyz`: every shard is connected to the gateway, the database answers
+//!   and the TTS backend's circuit isn't open
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serenity::gateway::{ConnectionStage, ShardManager};
+use songbird::Songbird;
+use std::convert::Infallible;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::db::Store;
+use crate::metrics;
+use crate::tts::circuit::CircuitState;
+
+/// What the endpoints report on.
+pub struct State {
+    pub voice: Arc<Songbird>,
+    pub shard_manager: Arc<ShardManager>,
+    pub store: Arc<dyn Store>,
+}
+
+/// Serves the endpoints on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, state: Arc<State>) {
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| handle(request, state.clone())))
+        }
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            error!("Failed to bind HTTP server to {}: {}", addr, e);
+            return;
+        }
+    };
+    info!("Serving metrics and health checks on http://{}", addr);
+    if let Err(e) = server.await {
+        error!("HTTP server error: {}", e);
+    }
+}
+
+async fn handle(request: Request<Body>, state: Arc<State>) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(metrics::render(&state.voice).await)),
+        (&Method::GET, "/healthz") => Response::builder().body(Body::from("ok\n")),
+        (&Method::GET, "/readyz") => {
+            let (ready, report) = readiness(&state).await;
+            let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+            Response::builder().status(status).body(Body::from(report))
+        }
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("Not found\n")),
+    };
+    Ok(response.unwrap_or_else(|_| Response::new(Body::empty())))
+}
+
+/// Runs every readiness check and returns whether all passed, along with one
+/// `name: result` line per check.
+async fn readiness(state: &State) -> (bool, String) {
+    let gateway = {
+        let runners = state.shard_manager.runners.lock().await;
+        if runners.is_empty() {
+            Err("no shards running".to_string())
+        } else {
+            let waiting = runners
+                .iter()
+                .filter(|(_, runner)| runner.stage != ConnectionStage::Connected)
+                .map(|(id, runner)| format!("shard {} is {}", id, runner.stage))
+                .collect::<Vec<_>>();
+            if waiting.is_empty() { Ok(()) } else { Err(waiting.join(", ")) }
+        }
+    };
+
+    let database = state.store.ping().await.map_err(|e| e.to_string());
+
+    let tts = match crate::tts::backend_state() {
+        CircuitState::Open => Err(format!("circuit {}", CircuitState::Open.as_str())),
+        _ => Ok(()),
+    };
+
+    let checks = [("gateway", gateway), ("database", database), ("tts", tts)];
+    let ready = checks.iter().all(|(_, result)| result.is_ok());
+    let report = checks
+        .iter()
+        .map(|(name, result)| match result {
+            Ok(()) => format!("{}: ok\n", name),
+            Err(e) => format!("{}: {}\n", name, e),
+        })
+        .collect();
+    (ready, report)
+}
+
+/// Asks a running bot at `addr` for `path` and returns the response body if
+/// it answered 200. Used by `discord-bot healthcheck`, so containers don't
+/// need curl.
+pub fn check(addr: SocketAddr, path: &str) -> Result<String, String> {
+    // A server listening on all interfaces is reachable on loopback
+    let addr = match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), addr.port()),
+        IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(Ipv6Addr::LOCALHOST.into(), addr.port()),
+        _ => addr,
+    };
+
+    let timeout = Duration::from_secs(5);
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|e| format!("Failed to connect to {}: {}", addr, e))?;
+    stream.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", path, addr)
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) == Some("200") {
+        Ok(body.to_string())
+    } else {
+        Err(format!("{} returned {:?}\n{}", path, status, body.trim_end()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Answers a single request with `response` and returns the bound address.
+    fn respond_once(response: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn check_passes_on_200() {
+        let addr = respond_once("HTTP/1.1 200 OK\r\n\r\ngateway: ok\n");
+        assert_eq!(check(addr, "/readyz").unwrap(), "gateway: ok\n");
+    }
+
+    #[test]
+    fn check_fails_on_503_and_unspecified_means_loopback() {
+        let addr = respond_once("HTTP/1.1 503 Service Unavailable\r\n\r\ngateway: shard 0 is connecting\n");
+        let unspecified = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), addr.port());
+
+        let error = check(unspecified, "/readyz").unwrap_err();
+        assert!(error.contains("503"), "{}", error);
+        assert!(error.contains("shard 0 is connecting"), "{}", error);
+    }
+}
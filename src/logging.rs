@@ -0,0 +1,43 @@
+//! Log output through `tracing`.
+//!
+//! Levels come from `RUST_LOG` (default `info`). Logs are human readable
+//! unless `logging.format = "json"`, and message content is replaced with its
+//! length while `logging.redact_content` is on.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_subscriber::EnvFilter;
+
+use crate::config::{LogFormat, LoggingConfig};
+
+static REDACT_CONTENT: AtomicBool = AtomicBool::new(true);
+
+/// Installs the global subscriber. Call once, before anything logs.
+pub fn init(config: &LoggingConfig) {
+    REDACT_CONTENT.store(config.redact_content, Ordering::SeqCst);
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().flatten_event(true).with_current_span(true).init(),
+    }
+}
+
+/// Wraps user-written text, e.g. a message or what is about to be spoken, so
+/// it only shows up in logs when redaction is off.
+pub fn content(text: &str) -> Content<'_> {
+    Content(text)
+}
+
+pub struct Content<'a>(&'a str);
+
+impl fmt::Display for Content<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if REDACT_CONTENT.load(Ordering::Relaxed) {
+            write!(f, "<redacted, {} chars>", self.0.chars().count())
+        } else {
+            f.write_str(self.0)
+        }
+    }
+}
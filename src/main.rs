@@ -3,173 +3,264 @@ mod api;
 mod db;
 mod handlers;
 mod audio;
+mod tts;
+mod shutdown;
+mod config;
+mod cli;
+mod logging;
+mod metrics;
+mod http;
 
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use dotenv::dotenv;
 use serenity::async_trait;
-use serenity::builder::{
-    CreateInteractionResponse, CreateInteractionResponseMessage, 
-    CreateAttachment, EditInteractionResponse, CreateInteractionResponseFollowup
-};
-use serenity::model::application::{Command, Interaction};
+use serenity::model::application::Interaction;
 use serenity::model::gateway::Ready;
-use serenity::model::channel::Message;
+use serenity::model::guild::Guild;
+use serenity::model::channel::{Message, Reaction};
 use serenity::model::voice::VoiceState;
 use serenity::prelude::*;
 use songbird::SerenityInit;
+use tracing::{debug, error, info, info_span, Instrument};
+
 
 struct Handler;
 
+static COMMANDS_REGISTERED: AtomicBool = AtomicBool::new(false);
+
 #[async_trait]
 impl EventHandler for Handler {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::Command(command) = interaction {
-            // println!("Received command interaction: {command:#?}");
-
-            let content = match command.data.name.as_str() {
-                "ping" => Some(commands::ping::run(&command.data.options())),
-                "id" => Some(commands::id::run(&command.data.options())),
-                "attachmentinput" => Some(commands::attachmentinput::run(&command.data.options())),
-                "setlanguage" => {
-                    match commands::setlanguage::run(&command, &ctx).await {
-                        Ok(response) => Some(response),
-                        Err(e) => Some(e),
-                    }
-                },
-                "modal" => {
-                    commands::modal::run(&ctx, &command).await.unwrap();
-                    None
-                },
-                "hiroyuki" => {
-                    match commands::hiroyuki::run(&ctx, &command, &command.data.options()).await {
-                        Ok(audio_data) => {
-                            // First, edit the deferred response to show success
-                            if let Err(e) = command
-                                .edit_response(&ctx.http,
-                                    EditInteractionResponse::new()
-                                        .content("✅ Voice generated successfully!"))
-                                .await
-                            {
-                                println!("Failed to send success message: {}", e);
-                            }
-
-                            // Then send the audio file as a follow-up message
-                            let followup = CreateInteractionResponseFollowup::new()
-                                .add_file(CreateAttachment::bytes(audio_data, "hiroyuki.wav"));
-                                
-                            if let Err(e) = command.create_followup(&ctx.http, followup).await {
-                                println!("Failed to send audio file: {}", e);
-                            }
-                        }
-                        Err(why) => {
-                            if let Err(e) = command
-                                .edit_response(&ctx.http, 
-                                    EditInteractionResponse::new()
-                                        .content(&why))
-                                .await
-                            {
-                                println!("Failed to send error response: {}", e);
-                            }
-                        }
-                    }
-                    None
-                },
-                "connect" => {
-                    match commands::voice::run(&command, &ctx).await {
-                        Ok(_) => None,
-                        Err(_) => None,
-                    }
-                },
-                "disconnect" => {
-                    match commands::voice::run_disconnect(&command, &ctx).await {
-                        Ok(_) => None,
-                        Err(_) => None,
-                    }
-                },
-                _ => Some("not implemented :(".to_string()),
-            };
-
-            if let Some(content) = content {
-                let data = CreateInteractionResponseMessage::new().content(content);
-                let builder = CreateInteractionResponse::Message(data);
-                if let Err(why) = command.create_response(&ctx.http, builder).await {
-                    println!("Cannot respond to slash command: {why}");
+        match interaction {
+            Interaction::Command(command) => {
+                let span = info_span!(
+                    "interaction",
+                    command = %command.data.name,
+                    guild_id = command.guild_id.map(|id| id.get()),
+                    channel_id = command.channel_id.get(),
+                    user_id = command.user.id.get(),
+                );
+                async {
+                    debug!("Received command interaction");
+                    metrics::COMMANDS.with_label_values(&[&command.data.name]).inc();
+
+                    commands::REGISTRY.dispatch(&ctx, &command).await;
+                }
+                .instrument(span)
+                .await;
+            }
+            Interaction::Autocomplete(command) => {
+                let span = info_span!(
+                    "autocomplete",
+                    command = %command.data.name,
+                    guild_id = command.guild_id.map(|id| id.get()),
+                    user_id = command.user.id.get(),
+                );
+                commands::REGISTRY.dispatch_autocomplete(&ctx, &command).instrument(span).await;
+            }
+            Interaction::Component(component) => {
+                let span = info_span!(
+                    "component",
+                    custom_id = %component.data.custom_id,
+                    guild_id = component.guild_id.map(|id| id.get()),
+                    channel_id = component.channel_id.get(),
+                    user_id = component.user.id.get(),
+                );
+                async {
+                    debug!("Received component interaction");
+                    commands::REGISTRY.dispatch_component(&ctx, &component).await;
+                }
+                .instrument(span)
+                .await;
+            }
+            Interaction::Modal(modal) => {
+                let span = info_span!(
+                    "modal",
+                    custom_id = %modal.data.custom_id,
+                    guild_id = modal.guild_id.map(|id| id.get()),
+                    channel_id = modal.channel_id.get(),
+                    user_id = modal.user.id.get(),
+                );
+                async {
+                    debug!("Received modal submission");
+                    commands::REGISTRY.dispatch_modal(&ctx, &modal).await;
                 }
+                .instrument(span)
+                .await;
             }
+            _ => {}
         }
     }
 
     async fn message(&self, ctx: Context, msg: Message) {
-        if let Err(e) = handlers::message::handle_message(&ctx, &msg).await {
-            println!("Error handling message: {}", e);
+        let span = info_span!(
+            "message",
+            message_id = msg.id.get(),
+            guild_id = msg.guild_id.map(|id| id.get()),
+            channel_id = msg.channel_id.get(),
+            user_id = msg.author.id.get(),
+        );
+        if let Err(e) = handlers::message::handle_message(&ctx, &msg).instrument(span.clone()).await {
+            span.in_scope(|| error!("Error handling message: {}", e));
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        let span = info_span!(
+            "reaction",
+            message_id = reaction.message_id.get(),
+            guild_id = reaction.guild_id.map(|id| id.get()),
+            channel_id = reaction.channel_id.get(),
+            user_id = reaction.user_id.map(|id| id.get()),
+        );
+        if let Err(e) = handlers::reaction::handle_reaction_add(&ctx, &reaction).instrument(span.clone()).await {
+            span.in_scope(|| error!("Error handling reaction: {}", e));
         }
     }
 
     async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
         if let Err(e) = handlers::voice_state::handle_voice_state_update(&ctx, old, new).await {
-            println!("❌ Error handling voice state update: {}", e);
+            error!("Error handling voice state update: {}", e);
         }
     }
 
+    async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: Option<bool>) {
+        handlers::restore::restore_guild(&ctx, guild.id).await;
+    }
+
     async fn ready(&self, ctx: Context, ready: Ready) {
-        println!("{} is connected!", ready.user.name);
-
-        let global_commands = Command::set_global_commands(&ctx.http, vec![
-            commands::ping::register(),
-            commands::id::register(),
-            commands::welcome::register(),
-            commands::numberinput::register(),
-            commands::attachmentinput::register(),
-            commands::modal::register(),
-            commands::wonderful_command::register(),
-            commands::hiroyuki::register(),
-            commands::voice::register(),
-            commands::voice::register_disconnect(),
-            commands::setlanguage::register(),
-        ])
-        .await;
-
-        match global_commands {
-            Ok(_commands) => println!("Successfully registered global commands!"),
-            Err(why) => println!("Error registering global commands: {why:?}"),
+        info!(shard_id = ctx.shard_id.0, "{} is connected!", ready.user.name);
+        handlers::restore::end_removed_guild_sessions(&ctx, &ready).await;
+
+        // Every shard fires `ready`, but commands only need registering once
+        if COMMANDS_REGISTERED.swap(true, Ordering::SeqCst) {
+            return;
         }
+        commands::access::load_owners(&ctx.http).await;
+        commands::sync::register(&ctx.http, &commands::REGISTRY, &config::get().commands).await;
     }
 }
 
 #[tokio::main]
 async fn main() {
+    commands::status::start_clock();
     dotenv().ok();
 
-    // Initialize database
-    if let Err(e) = db::init_db() {
-        eprintln!("Failed to initialize database: {:?}", e);
+    let args = match cli::Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
         return;
     }
 
+    // Load and validate the configuration before touching anything else
+    let config = match config::Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    if args.check_config {
+        println!("✅ Configuration is valid");
+        return;
+    }
+    if args.command == Some(cli::Command::Healthcheck) {
+        if !config.http.enabled {
+            eprintln!("❌ The HTTP server is disabled (http.enabled), nothing to check");
+            std::process::exit(1);
+        }
+        let addr = config.http.listen.parse().expect("http.listen is a valid address");
+        match http::check(addr, "/readyz") {
+            Ok(report) => {
+                print!("{}", report);
+                return;
+            }
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    config::init(config);
+    let config = config::get();
+    logging::init(&config.logging);
+
+    if args.purge_commands {
+        if let Err(e) = commands::sync::purge(&config.discord.token, &config.commands).await {
+            error!("Failed to purge commands: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Initialize database
+    info!("Initializing database at {}", config.database.path.display());
+    let store: Arc<dyn db::Store> = match db::SqliteStore::open(&config.database.path) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            error!("Failed to initialize database: {}", e);
+            return;
+        }
+    };
+
+    // Sessions are kept across restarts and rejoined once the guild caches are ready
+    info!("Database initialized successfully");
+
     // Initialize audio assets
     if let Err(e) = audio::ensure_audio_assets().await {
-        eprintln!("Failed to initialize audio assets: {:?}", e);
+        error!("Failed to initialize audio assets: {:?}", e);
         return;
     }
 
-    // Configure the client with your Discord bot token in the environment.
-    let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
+    // Keep a handle on the voice manager so shutdown can leave every call
+    let voice = songbird::Songbird::serenity();
 
     // Build our client.
-    let mut client = Client::builder(token, GatewayIntents::GUILDS 
-        | GatewayIntents::GUILD_VOICE_STATES 
-        | GatewayIntents::GUILD_MESSAGES
-        | GatewayIntents::MESSAGE_CONTENT)
+    let mut client = Client::builder(&config.discord.token, config.intents())
         .event_handler(Handler)
-        .register_songbird()
+        .type_map_insert::<db::StoreKey>(store.clone())
+        .register_songbird_with(voice.clone())
         .await
         .expect("Error creating client");
 
-    // Finally, start a single shard, and start listening to events.
-    //
+    if config.http.enabled {
+        // Validated along with the rest of the config
+        let addr = config.http.listen.parse().expect("http.listen is a valid address");
+        let state = http::State {
+            voice: voice.clone(),
+            shard_manager: client.shard_manager.clone(),
+            store,
+        };
+        tokio::spawn(http::serve(addr, Arc::new(state)));
+    }
+
+    client.data.write().await.insert::<commands::shards::ShardManagerKey>(client.shard_manager.clone());
+
+    // Leave every call and stop the shards on SIGTERM/SIGINT instead of dying mid-speech
+    let shard_manager = client.shard_manager.clone();
+    tokio::spawn(async move {
+        shutdown::wait_for_signal().await;
+        shutdown::run(shard_manager, voice).await;
+    });
+
     // Shards will automatically attempt to reconnect, and will perform exponential backoff until
     // it reconnects.
-    if let Err(why) = client.start().await {
-        println!("Client error: {why:?}");
+    let sharding = &config.sharding;
+    let result = match sharding.mode {
+        config::ShardingMode::Single => client.start().await,
+        config::ShardingMode::Auto => client.start_autosharded().await,
+        // Serenity treats the end of the range as inclusive
+        config::ShardingMode::Range => client.start_shard_range(sharding.first..sharding.last, sharding.total).await,
+    };
+    if let Err(why) = result {
+        error!("Client error: {why:?}");
     }
 }
\ No newline at end of file
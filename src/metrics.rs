@@ -0,0 +1,113 @@
+//! Prometheus metrics, served at `/metrics` by [`crate::http`].
+//!
+//! Counters and histograms are updated where things happen. Gauges that
+//! describe the voice connections are refreshed from songbird on every scrape.
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, Encoder, Gauge, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use songbird::Songbird;
+
+lazy_static! {
+    pub static ref ACTIVE_VOICE_SESSIONS: IntGauge = register_int_gauge!(
+        "bot_active_voice_sessions",
+        "Voice channels the bot is currently connected to"
+    ).unwrap();
+    pub static ref MESSAGES_READ: IntCounter = register_int_counter!(
+        "bot_messages_read_total",
+        "Messages synthesized and queued to be read aloud"
+    ).unwrap();
+    pub static ref SYNTHESIZED_CHARACTERS: IntCounterVec = register_int_counter_vec!(
+        "bot_synthesized_characters_total",
+        "Characters sent to a TTS backend",
+        &["backend"]
+    ).unwrap();
+    pub static ref SYNTHESIS_LATENCY: HistogramVec = register_histogram_vec!(
+        "bot_synthesis_latency_seconds",
+        "Time a TTS backend took to return audio",
+        &["backend"],
+        vec![0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0]
+    ).unwrap();
+    pub static ref BACKEND_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "bot_backend_errors_total",
+        "Failed requests to a TTS backend",
+        &["backend"]
+    ).unwrap();
+    pub static ref QUEUE_DEPTH: IntGaugeVec = register_int_gauge_vec!(
+        "bot_queue_depth",
+        "Utterances playing or waiting to be played in a guild",
+        &["guild_id"]
+    ).unwrap();
+    pub static ref CACHE_HITS: IntCounter = register_int_counter!(
+        "bot_synthesis_cache_hits_total",
+        "Utterances served from the synthesis cache"
+    ).unwrap();
+    pub static ref CACHE_MISSES: IntCounter = register_int_counter!(
+        "bot_synthesis_cache_misses_total",
+        "Utterances that had to be synthesized"
+    ).unwrap();
+    pub static ref CACHE_HIT_RATIO: Gauge = register_gauge!(
+        "bot_synthesis_cache_hit_ratio",
+        "Share of utterances served from the synthesis cache since startup"
+    ).unwrap();
+    pub static ref COMMANDS: IntCounterVec = register_int_counter_vec!(
+        "bot_command_invocations_total",
+        "Slash command invocations",
+        &["command"]
+    ).unwrap();
+}
+
+/// Refreshes the derived gauges and renders every metric in the text format.
+pub async fn render(voice: &Songbird) -> String {
+    refresh_voice_gauges(voice).await;
+
+    let hits = CACHE_HITS.get() as f64;
+    let total = hits + CACHE_MISSES.get() as f64;
+    CACHE_HIT_RATIO.set(if total > 0.0 { hits / total } else { 0.0 });
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        tracing::error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+async fn refresh_voice_gauges(voice: &Songbird) {
+    // Guilds the bot left since the last scrape shouldn't linger
+    QUEUE_DEPTH.reset();
+
+    // Collect the calls first so the manager's map isn't held across awaits
+    let calls = voice.iter().collect::<Vec<_>>();
+    let mut active = 0;
+    for (guild_id, handler_lock) in calls {
+        let handler = handler_lock.lock().await;
+        if handler.current_channel().is_none() {
+            continue;
+        }
+        active += 1;
+        QUEUE_DEPTH
+            .with_label_values(&[&guild_id.0.to_string()])
+            .set(handler.queue().len() as i64);
+    }
+    ACTIVE_VOICE_SESSIONS.set(active);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn render_includes_cache_ratio_and_sessions() {
+        CACHE_HITS.inc();
+        COMMANDS.with_label_values(&["ping"]).inc();
+
+        let rendered = render(&Songbird::serenity()).await;
+
+        assert!(rendered.contains("bot_active_voice_sessions 0"));
+        assert!(rendered.contains("bot_synthesis_cache_hit_ratio"));
+        assert!(rendered.contains("bot_command_invocations_total{command=\"ping\"}"));
+    }
+}
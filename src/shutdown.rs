@@ -0,0 +1,106 @@
+//! Graceful shutdown on SIGTERM/SIGINT.
+//!
+//! Once a signal arrives no new speech is started, queued utterances are
+//! dropped, the one that is currently playing in each call gets a few seconds
+//! to finish, and the bot leaves every call. Sessions stay in the database so they are restored on
+//! the next start.
+
+use serenity::gateway::ShardManager;
+use songbird::tracks::TrackHandle;
+use songbird::Songbird;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Whether new speech should be refused because the bot is shutting down.
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// Resolves once the process receives SIGINT or, on Unix, SIGTERM.
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+/// Stops accepting speech, drains the current utterances, leaves every call
+/// and shuts down all shards.
+pub async fn run(shard_manager: Arc<ShardManager>, voice: Arc<Songbird>) {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    info!("Shutting down, no longer accepting new speech");
+
+    // Collect the calls first so the manager's map isn't held across awaits
+    let calls = voice.iter().collect::<Vec<_>>();
+
+    // Drop everything that hasn't started playing yet. `speak` checks the flag
+    // under the same lock, so nothing is queued after a call is drained
+    let mut playing = Vec::new();
+    for (_, handler_lock) in &calls {
+        let handler = handler_lock.lock().await;
+        let queue = handler.queue();
+        while let Some(queued) = queue.dequeue(1) {
+            let _ = queued.stop();
+        }
+        playing.extend(queue.current());
+    }
+
+    let drain_timeout = Duration::from_secs(crate::config::get().shutdown.drain_timeout_secs);
+    if tokio::time::timeout(drain_timeout, drain(playing)).await.is_err() {
+        warn!("Utterances still playing after {:?}, cutting them off", drain_timeout);
+    }
+
+    for (guild_id, handler_lock) in calls {
+        let mut handler = handler_lock.lock().await;
+        if handler.current_channel().is_none() {
+            continue;
+        }
+        match handler.leave().await {
+            Ok(()) => info!(guild_id = guild_id.0.get(), "Left voice channel"),
+            Err(e) => error!(guild_id = guild_id.0.get(), "Failed to leave voice channel: {}", e),
+        }
+    }
+
+    shard_manager.shutdown_all().await;
+    info!("Shutdown complete");
+}
+
+/// Waits until every track has stopped playing.
+async fn drain(tracks: Vec<TrackHandle>) {
+    for track in tracks {
+        // The handle errors out once the track has ended and been dropped by the driver
+        while let Ok(state) = track.get_info().await {
+            if state.playing.is_done() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use lazy_static::lazy_static;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::model::voice::VoiceState;
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, error, Instrument};
+
+use crate::db;
+use crate::db::language::Language;
+use crate::db::VoiceAnnounceSettings;
+
+/// The parts of a voice state that can be announced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    channel_id: Option<ChannelId>,
+    streaming: bool,
+    video: bool,
+}
+
+impl Snapshot {
+    fn of(state: Option<&VoiceState>) -> Self {
+        Self {
+            channel_id: state.and_then(|s| s.channel_id),
+            streaming: state.and_then(|s| s.self_stream).unwrap_or(false),
+            video: state.map(|s| s.self_video).unwrap_or(false),
+        }
+    }
+}
+
+struct Pending {
+    /// The member's state before the first of the debounced updates.
+    before: Snapshot,
+    generation: u64,
+}
+
+lazy_static! {
+    static ref PENDING: Mutex<HashMap<(GuildId, UserId), Pending>> = Mutex::new(HashMap::new());
+}
+
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Joined,
+    Left,
+    MovedIn,
+    MovedOut,
+    StreamStarted,
+    StreamStopped,
+    VideoStarted,
+    VideoStopped,
+}
+
+impl Event {
+    fn enabled(&self, settings: &VoiceAnnounceSettings) -> bool {
+        match self {
+            Event::Joined => settings.joins,
+            Event::Left => settings.leaves,
+            Event::MovedIn | Event::MovedOut => settings.moves,
+            Event::StreamStarted | Event::StreamStopped => settings.streams,
+            Event::VideoStarted | Event::VideoStopped => settings.video,
+        }
+    }
+
+    fn phrase(&self, language: Language, name: &str) -> String {
+        match language {
+            Language::English => match self {
+                Event::Joined => format!("{} joined", name),
+                Event::Left => format!("{} left", name),
+                Event::MovedIn => format!("{} moved in", name),
+                Event::MovedOut => format!("{} moved to another channel", name),
+                Event::StreamStarted => format!("{} started screen sharing", name),
+                Event::StreamStopped => format!("{} stopped screen sharing", name),
+                Event::VideoStarted => format!("{} turned on their camera", name),
+                Event::VideoStopped => format!("{} turned off their camera", name),
+            },
+            Language::Japanese => match self {
+                Event::Joined => format!("{}が入室しました", name),
+                Event::Left => format!("{}が退室しました", name),
+                Event::MovedIn => format!("{}が移動してきました", name),
+                Event::MovedOut => format!("{}が別のチャンネルに移動しました", name),
+                Event::StreamStarted => format!("{}が画面共有を開始しました", name),
+                Event::StreamStopped => format!("{}が画面共有を終了しました", name),
+                Event::VideoStarted => format!("{}がカメラをオンにしました", name),
+                Event::VideoStopped => format!("{}がカメラをオフにしました", name),
+            },
+        }
+    }
+}
+
+/// Works out what happened in `bot_channel` between two voice states.
+fn events(before: Snapshot, after: Snapshot, bot_channel: ChannelId) -> Vec<Event> {
+    let was_here = before.channel_id == Some(bot_channel);
+    let is_here = after.channel_id == Some(bot_channel);
+
+    match (was_here, is_here) {
+        (false, true) if before.channel_id.is_none() => vec![Event::Joined],
+        (false, true) => vec![Event::MovedIn],
+        (true, false) if after.channel_id.is_none() => vec![Event::Left],
+        (true, false) => vec![Event::MovedOut],
+        (true, true) => {
+            let mut events = Vec::new();
+            if before.streaming != after.streaming {
+                events.push(if after.streaming { Event::StreamStarted } else { Event::StreamStopped });
+            }
+            if before.video != after.video {
+                events.push(if after.video { Event::VideoStarted } else { Event::VideoStopped });
+            }
+            events
+        }
+        (false, false) => Vec::new(),
+    }
+}
+
+/// Queues an announcement for a member's voice state change.
+///
+/// Updates are debounced per member: the announcement is only made once the
+/// member's state has been stable for the guild's debounce time, and it
+/// describes the net change, so a flapping connection that leaves and rejoins
+/// within the window is not announced at all.
+pub fn schedule(ctx: &Context, guild_id: GuildId, old: Option<&VoiceState>, new: &VoiceState) {
+    if new.user_id == ctx.cache.current_user().id {
+        return;
+    }
+    if new.member.as_ref().map(|member| member.user.bot).unwrap_or(false) {
+        return;
+    }
+
+    let before = Snapshot::of(old);
+    let after = Snapshot::of(Some(new));
+    let key = (guild_id, new.user_id);
+    let generation = GENERATION.fetch_add(1, Ordering::Relaxed);
+
+    {
+        let mut pending = PENDING.lock().unwrap();
+        match pending.get_mut(&key) {
+            Some(entry) => entry.generation = generation,
+            None => {
+                if before == after {
+                    // Mute, deafen and similar changes are not announced
+                    return;
+                }
+                pending.insert(key, Pending { before, generation });
+            }
+        }
+    }
+
+    let display_name = new
+        .member
+        .as_ref()
+        .map(|member| member.display_name().to_string())
+        .unwrap_or_else(|| new.user_id.to_string());
+    let ctx = ctx.clone();
+
+    tokio::spawn(async move {
+        let store = db::store(&ctx).await;
+        let settings = store.get_voice_announce_settings(guild_id.get()).await.unwrap_or_else(|e| {
+            error!("Failed to get voice announce settings: {}", e);
+            VoiceAnnounceSettings::default()
+        });
+        tokio::time::sleep(Duration::from_secs(settings.debounce_secs)).await;
+
+        let before = {
+            let mut pending = PENDING.lock().unwrap();
+            match pending.get(&key) {
+                Some(entry) if entry.generation == generation => pending.remove(&key).unwrap().before,
+                // A newer update for this member took over
+                _ => return,
+            }
+        };
+
+        let Some(manager) = songbird::get(&ctx).await else {
+            return;
+        };
+        let Some(handler_lock) = manager.get(guild_id) else {
+            return;
+        };
+        let Some(bot_channel) = handler_lock.lock().await.current_channel() else {
+            return;
+        };
+        let bot_channel = ChannelId::new(bot_channel.0.get());
+
+        let events = events(before, after, bot_channel)
+            .into_iter()
+            .filter(|event| event.enabled(&settings))
+            .collect::<Vec<_>>();
+        if events.is_empty() {
+            return;
+        }
+
+        let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+        let name = super::names::reading_or(&ctx, guild_id, key.1, &display_name).await;
+        let name = super::apply_dictionary(&ctx, guild_id, &name).await;
+        for event in events {
+            let phrase = event.phrase(language, &name);
+            debug!(guild_id = %guild_id, phrase = %crate::logging::content(&phrase), "Announcing voice event");
+            if let Err(e) = super::speak(&ctx, guild_id, &phrase).await {
+                error!("Failed to announce voice event: {}", e);
+            }
+        }
+    }.in_current_span());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOT: u64 = 1;
+    const OTHER: u64 = 2;
+    const ELSEWHERE: u64 = 3;
+
+    fn state(channel: Option<u64>, streaming: bool, video: bool) -> Snapshot {
+        Snapshot { channel_id: channel.map(ChannelId::new), streaming, video }
+    }
+
+    fn idle(channel: Option<u64>) -> Snapshot {
+        state(channel, false, false)
+    }
+
+    #[test]
+    fn events_are_relative_to_the_bots_channel() {
+        let cases = [
+            (idle(None), idle(Some(BOT)), vec![Event::Joined]),
+            (idle(Some(BOT)), idle(None), vec![Event::Left]),
+            (idle(Some(OTHER)), idle(Some(BOT)), vec![Event::MovedIn]),
+            (idle(Some(BOT)), idle(Some(OTHER)), vec![Event::MovedOut]),
+            (idle(Some(BOT)), state(Some(BOT), true, false), vec![Event::StreamStarted]),
+            (state(Some(BOT), true, false), idle(Some(BOT)), vec![Event::StreamStopped]),
+            (idle(Some(BOT)), state(Some(BOT), false, true), vec![Event::VideoStarted]),
+            (state(Some(BOT), false, true), idle(Some(BOT)), vec![Event::VideoStopped]),
+            (idle(Some(BOT)), state(Some(BOT), true, true), vec![Event::StreamStarted, Event::VideoStarted]),
+            (idle(Some(BOT)), idle(Some(BOT)), vec![]),
+            // Nothing the bot's channel would hear
+            (idle(Some(OTHER)), idle(Some(ELSEWHERE)), vec![]),
+            (idle(None), idle(Some(OTHER)), vec![]),
+            (idle(Some(OTHER)), state(Some(OTHER), true, true), vec![]),
+        ];
+        for (before, after, expected) in cases {
+            assert_eq!(events(before, after, ChannelId::new(BOT)), expected, "{:?} -> {:?}", before, after);
+        }
+    }
+
+    #[test]
+    fn joining_while_streaming_is_a_join() {
+        assert_eq!(events(idle(None), state(Some(BOT), true, true), ChannelId::new(BOT)), vec![Event::Joined]);
+    }
+}
@@ -0,0 +1,80 @@
+//! In-memory cache of synthesized audio.
+//!
+//! Join/leave announcements and short replies repeat a lot, so the most
+//! recent utterances are kept by their exact text. The oldest entry is
+//! evicted first once the cache is full.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+pub struct SynthesisCache {
+    capacity: usize,
+    entries: HashMap<String, Arc<Vec<u8>>>,
+    order: VecDeque<String>,
+}
+
+impl SynthesisCache {
+    /// A capacity of 0 disables the cache.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// How many utterances are cached, and how many fit.
+    pub fn usage(&self) -> (usize, usize) {
+        (self.entries.len(), self.capacity)
+    }
+
+    pub fn get(&self, text: &str) -> Option<Arc<Vec<u8>>> {
+        self.entries.get(text).cloned()
+    }
+
+    pub fn insert(&mut self, text: &str, audio: Arc<Vec<u8>>) {
+        if self.capacity == 0 || self.entries.contains_key(text) {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        self.entries.insert(text.to_string(), audio);
+        self.order.push_back(text.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audio(byte: u8) -> Arc<Vec<u8>> {
+        Arc::new(vec![byte])
+    }
+
+    #[test]
+    fn evicts_the_oldest_entry() {
+        let mut cache = SynthesisCache::new(2);
+        cache.insert("a", audio(1));
+        cache.insert("b", audio(2));
+        cache.insert("c", audio(3));
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(audio(2)));
+        assert_eq!(cache.get("c"), Some(audio(3)));
+        assert_eq!(cache.usage(), (2, 2));
+    }
+
+    #[test]
+    fn zero_capacity_caches_nothing() {
+        let mut cache = SynthesisCache::new(0);
+        cache.insert("a", audio(1));
+
+        assert_eq!(cache.get("a"), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Circuit breaker for the TTS backend.
+//!
+//! After enough consecutive failures the circuit opens and synthesis is
+//! refused without calling the backend. Once the cooldown has passed the
+//! circuit is half-open: the next request is let through, and its outcome
+//! closes the circuit again or reopens it for another cooldown.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half-open",
+        }
+    }
+}
+
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            consecutive_failures: 0,
+            opened_at: None,
+        }
+    }
+
+    pub fn state(&self, now: Instant) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if now.duration_since(opened_at) < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether a request may be sent to the backend.
+    pub fn allows(&self, now: Instant) -> bool {
+        self.state(now) != CircuitState::Open
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+    }
+
+    pub fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+        let trial_failed = self.state(now) == CircuitState::HalfOpen;
+        if trial_failed || self.consecutive_failures >= self.failure_threshold {
+            self.opened_at = Some(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(3, COOLDOWN);
+
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), CircuitState::Closed);
+        // A success in between starts the count over
+        breaker.record_success();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(breaker.allows(now));
+
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(now), CircuitState::Open);
+        assert!(!breaker.allows(now + Duration::from_secs(29)));
+    }
+
+    #[test]
+    fn half_open_trial_closes_or_reopens() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(1, COOLDOWN);
+        breaker.record_failure(now);
+
+        let later = now + COOLDOWN;
+        assert_eq!(breaker.state(later), CircuitState::HalfOpen);
+        breaker.record_failure(later);
+        assert_eq!(breaker.state(later), CircuitState::Open);
+
+        let even_later = later + COOLDOWN;
+        assert!(breaker.allows(even_later));
+        breaker.record_success();
+        assert_eq!(breaker.state(even_later), CircuitState::Closed);
+    }
+}
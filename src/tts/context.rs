@@ -0,0 +1,214 @@
+use serenity::model::channel::{Attachment, Message, Poll};
+use serenity::model::id::GuildId;
+use serenity::prelude::*;
+use tracing::error;
+
+use crate::db;
+use crate::db::language::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttachmentKind {
+    Image,
+    Video,
+    Audio,
+    File,
+}
+
+impl AttachmentKind {
+    const ALL: [AttachmentKind; 4] = [
+        AttachmentKind::Image,
+        AttachmentKind::Video,
+        AttachmentKind::Audio,
+        AttachmentKind::File,
+    ];
+
+    fn of(attachment: &Attachment) -> Self {
+        let content_type = attachment.content_type.as_deref().unwrap_or_default();
+        if content_type.starts_with("image/") {
+            return AttachmentKind::Image;
+        }
+        if content_type.starts_with("video/") {
+            return AttachmentKind::Video;
+        }
+        if content_type.starts_with("audio/") {
+            return AttachmentKind::Audio;
+        }
+
+        // Discord does not always send a content type, fall back to the extension
+        let extension = attachment
+            .filename
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "heic" => AttachmentKind::Image,
+            "mp4" | "mov" | "webm" | "mkv" | "avi" => AttachmentKind::Video,
+            "mp3" | "wav" | "ogg" | "flac" | "m4a" => AttachmentKind::Audio,
+            _ => AttachmentKind::File,
+        }
+    }
+
+    fn phrase(&self, language: Language, count: usize) -> String {
+        match (language, self) {
+            (Language::Japanese, AttachmentKind::Image) => format!("画像{}枚", count),
+            (Language::Japanese, AttachmentKind::Video) => format!("動画{}本", count),
+            (Language::Japanese, AttachmentKind::Audio) => format!("音声{}件", count),
+            (Language::Japanese, AttachmentKind::File) => format!("ファイル{}件", count),
+            (Language::English, kind) => {
+                let noun = match kind {
+                    AttachmentKind::Image => "image",
+                    AttachmentKind::Video => "video",
+                    AttachmentKind::Audio => "audio file",
+                    AttachmentKind::File => "file",
+                };
+                if count == 1 {
+                    format!("1 {}", noun)
+                } else {
+                    format!("{} {}s", count, noun)
+                }
+            }
+        }
+    }
+}
+
+/// One phrase per kind of attachment, e.g. "画像2枚" or "1 video".
+fn attachment_phrases(attachments: &[Attachment], language: Language) -> Vec<String> {
+    AttachmentKind::ALL
+        .into_iter()
+        .filter_map(|kind| {
+            let count = attachments.iter().filter(|a| AttachmentKind::of(a) == kind).count();
+            (count > 0).then(|| kind.phrase(language, count))
+        })
+        .collect()
+}
+
+/// Builds everything that should be spoken for `msg`: the reply target, the
+/// normalized text without its inline-skipped parts and, depending on the
+/// guild's read settings, phrases for attachments, stickers, embed titles and
+/// polls.
+///
+/// Returns an empty string if there is nothing worth reading.
+pub async fn compose(ctx: &Context, guild_id: GuildId, msg: &Message) -> String {
+    let store = db::store(ctx).await;
+    let settings = store.get_read_settings(guild_id.get()).await.unwrap_or_else(|e| {
+        error!("Failed to get read settings: {}", e);
+        db::ReadSettings::default()
+    });
+    let language = store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English);
+
+    let mut parts = Vec::new();
+
+    let content = super::normalize(ctx, guild_id, &super::skip::strip_inline(&msg.content)).await;
+    if !content.trim().is_empty() {
+        parts.push(content);
+    }
+
+    if settings.attachments {
+        parts.extend(attachment_phrases(&msg.attachments, language));
+    }
+
+    if settings.stickers {
+        for sticker in &msg.sticker_items {
+            let name = super::apply_dictionary(ctx, guild_id, &sticker.name).await;
+            parts.push(match language {
+                Language::English => format!("Sticker {}", name),
+                Language::Japanese => format!("スタンプ {}", name),
+            });
+        }
+    }
+
+    if settings.embeds {
+        for title in msg.embeds.iter().filter_map(|embed| embed.title.as_deref()) {
+            let title = super::normalize(ctx, guild_id, title).await;
+            if !title.trim().is_empty() {
+                parts.push(title);
+            }
+        }
+    }
+
+    if settings.polls {
+        if let Some(poll) = &msg.poll {
+            parts.push(poll_summary(ctx, guild_id, language, poll).await);
+        }
+    }
+
+    if parts.is_empty() {
+        return String::new();
+    }
+
+    if settings.replies {
+        if let Some(replied) = &msg.referenced_message {
+            let name = super::names::spoken_name(ctx, guild_id, replied).await;
+            let name = super::apply_dictionary(ctx, guild_id, &name).await;
+            parts.insert(0, match language {
+                Language::English => format!("Reply to {}", name),
+                Language::Japanese => format!("{}さんへの返信", name),
+            });
+        }
+    }
+
+    let separator = match language {
+        Language::English => ", ",
+        Language::Japanese => "、",
+    };
+    parts.join(separator)
+}
+
+async fn poll_summary(ctx: &Context, guild_id: GuildId, language: Language, poll: &Poll) -> String {
+    let question = super::normalize(ctx, guild_id, poll.question.text.as_deref().unwrap_or_default()).await;
+    let mut answers = Vec::new();
+    for text in poll.answers.iter().filter_map(|answer| answer.poll_media.text.as_deref()) {
+        answers.push(super::normalize(ctx, guild_id, text).await);
+    }
+
+    match language {
+        Language::English => format!("Poll: {}. {} options: {}", question, answers.len(), answers.join(", ")),
+        Language::Japanese => format!("投票「{}」、選択肢{}つ、{}", question, answers.len(), answers.join("、")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn attachment(filename: &str, content_type: Option<&str>) -> Attachment {
+        serde_json::from_value(json!({
+            "id": "1",
+            "filename": filename,
+            "size": 1,
+            "url": "https://cdn.example/file",
+            "proxy_url": "https://media.example/file",
+            "content_type": content_type,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn kind_comes_from_content_type_then_extension() {
+        assert_eq!(AttachmentKind::of(&attachment("photo", Some("image/png"))), AttachmentKind::Image);
+        assert_eq!(AttachmentKind::of(&attachment("clip.bin", Some("video/mp4"))), AttachmentKind::Video);
+        assert_eq!(AttachmentKind::of(&attachment("voice.ogg", Some("audio/ogg"))), AttachmentKind::Audio);
+        // No content type: the extension decides, in any case
+        assert_eq!(AttachmentKind::of(&attachment("Photo.JPG", None)), AttachmentKind::Image);
+        assert_eq!(AttachmentKind::of(&attachment("song.mp3", None)), AttachmentKind::Audio);
+        // Unknown types are plain files
+        assert_eq!(AttachmentKind::of(&attachment("notes.txt", Some("text/plain"))), AttachmentKind::File);
+        assert_eq!(AttachmentKind::of(&attachment("README", None)), AttachmentKind::File);
+    }
+
+    #[test]
+    fn mixed_attachments_are_counted_per_kind() {
+        let attachments = [
+            attachment("a.png", Some("image/png")),
+            attachment("b.webp", None),
+            attachment("c.mov", None),
+            attachment("d.zip", Some("application/zip")),
+        ];
+        assert_eq!(attachment_phrases(&attachments, Language::Japanese), vec!["画像2枚", "動画1本", "ファイル1件"]);
+        assert_eq!(attachment_phrases(&attachments, Language::English), vec!["2 images", "1 video", "1 file"]);
+        assert_eq!(AttachmentKind::Audio.phrase(Language::English, 3), "3 audio files");
+        assert_eq!(AttachmentKind::Audio.phrase(Language::Japanese, 3), "音声3件");
+        assert!(attachment_phrases(&[], Language::English).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+//! Reactions that show what happens to a message being read.
+//!
+//! While a message is playing the bot reacts with [`SPEAKING`] and removes the
+//! reaction when playback ends. A message that couldn't be read gets
+//! [`FAILED`] instead, so failures aren't silent.
+
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::channel::ReactionType;
+use serenity::model::id::{ChannelId, MessageId};
+use songbird::events::{Event, EventContext, EventHandler, TrackEvent};
+use songbird::tracks::TrackHandle;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Shown while the message is being spoken. Reacting with it asks for the
+/// message to be read again.
+pub const SPEAKING: &str = "🔊";
+
+/// Shown on a message that couldn't be read.
+pub const FAILED: &str = "⚠️";
+
+/// Whether `reaction` is [`SPEAKING`].
+pub fn is_speaking(reaction: &ReactionType) -> bool {
+    matches!(reaction, ReactionType::Unicode(emoji) if emoji == SPEAKING)
+}
+
+/// Reacts with [`SPEAKING`] on the message while `track` plays.
+pub fn attach(http: Arc<Http>, track: &TrackHandle, channel_id: ChannelId, message_id: MessageId) {
+    let reaction = |action| Reaction { http: http.clone(), channel_id, message_id, action };
+    let events = [
+        (TrackEvent::Play, Action::Add(SPEAKING)),
+        (TrackEvent::End, Action::Remove(SPEAKING)),
+        (TrackEvent::Error, Action::Remove(SPEAKING)),
+        (TrackEvent::Error, Action::Add(FAILED)),
+    ];
+    for (event, action) in events {
+        if let Err(e) = track.add_event(Event::Track(event), reaction(action)) {
+            debug!("Failed to watch track for the speaking indicator: {}", e);
+        }
+    }
+}
+
+/// Marks the message as not read with [`FAILED`].
+pub async fn mark_failed(http: &Http, channel_id: ChannelId, message_id: MessageId) {
+    react(http, channel_id, message_id, Action::Add(FAILED)).await;
+}
+
+#[derive(Clone, Copy)]
+enum Action {
+    Add(&'static str),
+    Remove(&'static str),
+}
+
+async fn react(http: &Http, channel_id: ChannelId, message_id: MessageId, action: Action) {
+    // Missing the Add Reactions permission shouldn't stop the reading
+    let result = match action {
+        Action::Add(emoji) => {
+            channel_id
+                .create_reaction(http, message_id, ReactionType::Unicode(emoji.to_string()))
+                .await
+        }
+        Action::Remove(emoji) => {
+            channel_id
+                .delete_reaction(http, message_id, None, ReactionType::Unicode(emoji.to_string()))
+                .await
+        }
+    };
+    if let Err(e) = result {
+        warn!(message_id = %message_id, "Failed to update reaction: {}", e);
+    }
+}
+
+/// Applies its action when the track event it's registered for fires.
+struct Reaction {
+    http: Arc<Http>,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    action: Action,
+}
+
+#[async_trait]
+impl EventHandler for Reaction {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        react(&self.http, self.channel_id, self.message_id, self.action).await;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_speaker_emoji_rereads() {
+        assert!(is_speaking(&ReactionType::Unicode("🔊".to_string())));
+        assert!(!is_speaking(&ReactionType::Unicode(FAILED.to_string())));
+        assert!(!is_speaking(&ReactionType::Custom { animated: false, id: 1.into(), name: Some("🔊".to_string()) }));
+    }
+}
@@ -0,0 +1,286 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::*;
+use songbird::input::Input;
+use songbird::tracks::TrackHandle;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info};
+
+use crate::api::hiroyuki;
+use crate::config::VoiceConfig;
+use crate::db;
+use crate::metrics;
+
+pub mod announce;
+pub mod cache;
+pub mod circuit;
+pub mod context;
+pub mod indicator;
+pub mod names;
+pub mod skip;
+pub mod voices;
+
+lazy_static! {
+    static ref URL_REGEX: Regex = Regex::new(r"https?://[^\s]+").unwrap();
+    static ref MENTION_REGEX: Regex = Regex::new(r"<@!?(\d+)>").unwrap();
+    static ref CACHE: Mutex<cache::SynthesisCache> =
+        Mutex::new(cache::SynthesisCache::new(crate::config::get().tts.cache_entries));
+    static ref BREAKER: Mutex<circuit::CircuitBreaker> = Mutex::new(circuit::CircuitBreaker::new(
+        crate::config::get().tts.circuit_failure_threshold,
+        Duration::from_secs(crate::config::get().tts.circuit_cooldown_secs),
+    ));
+    /// How long the most recent request to the backend took.
+    static ref LAST_LATENCY: Mutex<Option<Duration>> = Mutex::new(None);
+}
+
+/// The state of the TTS backend's circuit breaker.
+pub fn backend_state() -> circuit::CircuitState {
+    BREAKER.lock().unwrap().state(Instant::now())
+}
+
+/// How long the most recent request to the TTS backend took, if there was one.
+pub fn last_backend_latency() -> Option<Duration> {
+    *LAST_LATENCY.lock().unwrap()
+}
+
+/// How many utterances the synthesis cache holds, and how many it can hold.
+pub fn cache_usage() -> (usize, usize) {
+    CACHE.lock().unwrap().usage()
+}
+
+/// Turns raw message text into what should be spoken: mentions become names,
+/// URLs are shortened and the guild's pronunciation dictionary is applied.
+pub async fn normalize(ctx: &Context, guild_id: GuildId, content: &str) -> String {
+    let store = db::store(ctx).await;
+
+    // Look up the names of mentioned users before replacing them
+    let mut names = HashMap::new();
+    let mentioned = MENTION_REGEX
+        .captures_iter(content)
+        .filter_map(|caps| caps[1].parse::<u64>().ok())
+        .collect::<HashSet<_>>();
+    for user_id in mentioned {
+        let display_name = guild_id
+            .to_guild_cached(&ctx.cache)
+            .and_then(|guild| {
+                guild.members
+                    .get(&UserId::new(user_id))
+                    .map(|member| member.display_name().to_string())
+            });
+        match store.get_spoken_name(guild_id.get(), user_id).await {
+            Ok(Some(reading)) => {
+                names.insert(user_id, reading);
+            }
+            Ok(None) => {
+                if let Some(display_name) = display_name {
+                    names.insert(user_id, display_name);
+                }
+            }
+            Err(e) => error!("Failed to get spoken name: {}", e),
+        }
+    }
+
+    // Replace user mentions with their spoken names using regex
+    let mut processed_content = MENTION_REGEX.replace_all(content, |caps: &regex::Captures| {
+        caps[1]
+            .parse::<u64>()
+            .ok()
+            .and_then(|user_id| names.get(&user_id).cloned())
+            .unwrap_or_else(|| caps[0].to_string())
+    }).to_string();
+
+    // Replace URLs with リンク省略
+    processed_content = URL_REGEX.replace_all(&processed_content, "リンク省略").to_string();
+
+    apply_dictionary(ctx, guild_id, &processed_content).await
+}
+
+/// Replaces every dictionary word in `text` with its reading.
+pub async fn apply_dictionary(ctx: &Context, guild_id: GuildId, text: &str) -> String {
+    match db::store(ctx).await.get_dictionary(guild_id.get()).await {
+        Ok(entries) => replace_words(entries, text),
+        Err(e) => {
+            error!("Failed to load dictionary for guild {}: {}", guild_id, e);
+            text.to_string()
+        }
+    }
+}
+
+/// Replaces every `(word, reading)` pair in `text`, ignoring case. Longer
+/// words win over shorter ones so that e.g. "Rustacean" is not read as
+/// "Rust" + "acean".
+fn replace_words(mut entries: Vec<(String, String)>, text: &str) -> String {
+    if entries.is_empty() {
+        return text.to_string();
+    }
+
+    entries.sort_by_key(|(word, _)| std::cmp::Reverse(word.chars().count()));
+    let pattern = entries
+        .iter()
+        .map(|(word, _)| regex::escape(word))
+        .collect::<Vec<_>>()
+        .join("|");
+    let regex = match Regex::new(&format!("(?i){}", pattern)) {
+        Ok(regex) => regex,
+        Err(e) => {
+            error!("Failed to build dictionary pattern: {}", e);
+            return text.to_string();
+        }
+    };
+
+    regex.replace_all(text, |caps: &regex::Captures| {
+        let matched = caps[0].to_lowercase();
+        entries
+            .iter()
+            .find(|(word, _)| word.to_lowercase() == matched)
+            .map(|(_, reading)| reading.clone())
+            .unwrap_or_else(|| caps[0].to_string())
+    }).to_string()
+}
+
+/// Cuts `text` off after `max_chars` characters, saying that the rest was
+/// left out.
+fn truncate(text: &str, max_chars: u32) -> String {
+    match text.char_indices().nth(max_chars as usize) {
+        Some((end, _)) => format!("{}、以下略", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Returns the audio for `text` in `voice`, from the cache if it was
+/// synthesized recently.
+pub async fn synthesize(voice: &VoiceConfig, text: &str) -> Result<Arc<Vec<u8>>, String> {
+    // The same text sounds different in every voice
+    let key = format!("{}\n{}", voice.id, text);
+    if let Some(audio) = CACHE.lock().unwrap().get(&key) {
+        metrics::CACHE_HITS.inc();
+        return Ok(audio);
+    }
+    metrics::CACHE_MISSES.inc();
+
+    let backend = hiroyuki::BACKEND;
+    if !BREAKER.lock().unwrap().allows(Instant::now()) {
+        return Err("The TTS backend is unavailable, try again later".to_string());
+    }
+    metrics::SYNTHESIZED_CHARACTERS
+        .with_label_values(&[backend])
+        .inc_by(text.chars().count() as u64);
+    let started = Instant::now();
+    let result = hiroyuki::get_voice(&voice.api_url, text).await;
+    let latency = started.elapsed();
+    metrics::SYNTHESIS_LATENCY
+        .with_label_values(&[backend])
+        .observe(latency.as_secs_f64());
+    *LAST_LATENCY.lock().unwrap() = Some(latency);
+
+    let audio = match result {
+        Ok(audio) => {
+            BREAKER.lock().unwrap().record_success();
+            Arc::new(audio)
+        }
+        Err(e) => {
+            metrics::BACKEND_ERRORS.with_label_values(&[backend]).inc();
+            BREAKER.lock().unwrap().record_failure(Instant::now());
+            return Err(format!("Failed to get {} voice: {}", voice.name, e));
+        }
+    };
+    CACHE.lock().unwrap().insert(&key, audio.clone());
+    Ok(audio)
+}
+
+/// Synthesizes `text` in the guild's voice, cut off at its length limit, and
+/// queues it in the guild's current voice call. Returns the queued track, or
+/// `None` if the bot isn't in a call or is shutting down.
+pub async fn speak(ctx: &Context, guild_id: GuildId, text: &str) -> Result<Option<TrackHandle>, String> {
+    let manager = songbird::get(ctx)
+        .await
+        .ok_or("Failed to get voice client")?
+        .clone();
+
+    let handler_lock = match manager.get(guild_id) {
+        Some(handler) => handler,
+        None => {
+            debug!("Bot is not in a voice channel");
+            return Ok(None);
+        }
+    };
+
+    if crate::shutdown::is_shutting_down() {
+        info!("Shutting down, not speaking");
+        return Ok(None);
+    }
+
+    let settings = db::store(ctx)
+        .await
+        .get_speech_settings(guild_id.get())
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to get speech settings for guild {}: {}", guild_id, e);
+            db::SpeechSettings::default()
+        });
+    let voice = voices::find(settings.voice.as_deref());
+    let text = truncate(text, settings.max_chars);
+
+    debug!(text = %crate::logging::content(&text), voice = %voice.id, "Generating voice for message");
+
+    let audio_data = synthesize(&voice, &text).await?;
+
+    // Create input from the audio bytes
+    let input = Input::from(audio_data.as_ref().clone());
+
+    let mut handler = handler_lock.lock().await;
+
+    // Synthesis can take a while, so check again. Checking under the call's
+    // lock means shutdown either sees this track when it drains the queue or
+    // this sees the flag, never neither
+    if crate::shutdown::is_shutting_down() {
+        info!("Shutting down, not playing audio");
+        return Ok(None);
+    }
+
+    // Queue the audio so utterances don't talk over each other
+    debug!(queue_depth = handler.queue().len(), "Queueing audio");
+    let track = handler.enqueue_input(input).await;
+
+    Ok(Some(track))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_counts_characters() {
+        assert_eq!(truncate("こんにちは", 5), "こんにちは");
+        assert_eq!(truncate("こんにちは世界", 5), "こんにちは、以下略");
+    }
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(word, reading)| (word.to_string(), reading.to_string())).collect()
+    }
+
+    #[test]
+    fn longer_words_win() {
+        let dictionary = entries(&[("ab", "エービー"), ("abc", "エービーシー")]);
+        assert_eq!(replace_words(dictionary.clone(), "abc ab abcab"), "エービーシー エービー エービーシーエービー");
+        assert_eq!(replace_words(dictionary, "a b"), "a b");
+        assert_eq!(replace_words(Vec::new(), "abc"), "abc");
+    }
+
+    #[test]
+    fn words_match_ignoring_case() {
+        let dictionary = entries(&[("Rust", "ラスト")]);
+        assert_eq!(replace_words(dictionary, "rust RUST Rust"), "ラスト ラスト ラスト");
+        let dictionary = entries(&[("ÄPFEL", "アプフェル")]);
+        assert_eq!(replace_words(dictionary, "äpfel"), "アプフェル");
+    }
+
+    #[test]
+    fn non_ascii_words_are_replaced() {
+        let dictionary = entries(&[("草", "くさ"), ("草生える", "くさはえる"), ("w", "わら")]);
+        assert_eq!(replace_words(dictionary, "草生える草w"), "くさはえるくさわら");
+    }
+}
@@ -0,0 +1,66 @@
+use lazy_static::lazy_static;
+use serenity::model::channel::Message;
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::error;
+
+use crate::db;
+use crate::db::language::Language;
+use crate::db::name_announce::NameAnnounce;
+
+lazy_static! {
+    /// The author of the last message read in each guild, for `NameAnnounce::OnChange`.
+    static ref LAST_SPEAKER: Mutex<HashMap<GuildId, UserId>> = Mutex::new(HashMap::new());
+}
+
+/// The name a message author is read as: their `/callme` reading if they set
+/// one, otherwise their server nickname or display name.
+pub async fn spoken_name(ctx: &Context, guild_id: GuildId, msg: &Message) -> String {
+    let display_name = msg.member
+        .as_ref()
+        .and_then(|member| member.nick.as_deref())
+        .unwrap_or_else(|| msg.author.display_name());
+    reading_or(ctx, guild_id, msg.author.id, display_name).await
+}
+
+/// The `/callme` reading of `user_id`, or `display_name` if they have none.
+pub async fn reading_or(ctx: &Context, guild_id: GuildId, user_id: UserId, display_name: &str) -> String {
+    match db::store(ctx).await.get_spoken_name(guild_id.get(), user_id.get()).await {
+        Ok(Some(reading)) => return reading,
+        Ok(None) => {}
+        Err(e) => error!("Failed to get spoken name: {}", e),
+    }
+
+    display_name.to_string()
+}
+
+/// Prefixes `text` with the author's name if the guild's name announce mode
+/// asks for it, and remembers the author as the guild's last speaker.
+pub async fn with_author_name(ctx: &Context, guild_id: GuildId, msg: &Message, text: String) -> String {
+    let previous = LAST_SPEAKER.lock().unwrap().insert(guild_id, msg.author.id);
+
+    let store = db::store(ctx).await;
+    let mode = store.get_name_announce(guild_id.get()).await.unwrap_or(NameAnnounce::Never);
+    let announce = match mode {
+        NameAnnounce::Always => true,
+        NameAnnounce::OnChange => previous != Some(msg.author.id),
+        NameAnnounce::Never => false,
+    };
+    if !announce {
+        return text;
+    }
+
+    let name = super::apply_dictionary(ctx, guild_id, &spoken_name(ctx, guild_id, msg).await).await;
+    match store.get_guild_language(guild_id.get()).await.unwrap_or(Language::English) {
+        Language::English => format!("{}, {}", name, text),
+        Language::Japanese => format!("{}、{}", name, text),
+    }
+}
+
+/// Forgets the last speaker so the next message in the guild is announced
+/// again, e.g. after the bot leaves the call.
+pub fn forget_guild(guild_id: GuildId) {
+    LAST_SPEAKER.lock().unwrap().remove(&guild_id);
+}
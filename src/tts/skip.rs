@@ -0,0 +1,86 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serenity::model::id::GuildId;
+use serenity::prelude::*;
+use tracing::error;
+
+use crate::db;
+
+/// Skip prefixes used until a guild configures its own.
+pub const DEFAULT_SKIP_PREFIXES: [&str; 2] = [";", "//"];
+
+/// Text between a pair of these markers is not read. An unpaired marker hides
+/// the rest of the message.
+pub const INLINE_MARKER: &str = ";;";
+
+lazy_static! {
+    /// Commands meant for other bots, e.g. `!play` or `/skip` typed as text.
+    static ref BOT_COMMAND_REGEX: Regex = Regex::new(r"^(?:![A-Za-z]|/)").unwrap();
+}
+
+/// The guild's skip prefixes, falling back to the defaults.
+pub async fn prefixes(ctx: &Context, guild_id: GuildId) -> Vec<String> {
+    match db::store(ctx).await.get_skip_prefixes(guild_id.get()).await {
+        Ok(Some(prefixes)) => prefixes,
+        Ok(None) => DEFAULT_SKIP_PREFIXES.iter().map(|p| p.to_string()).collect(),
+        Err(e) => {
+            error!("Failed to get skip prefixes: {}", e);
+            DEFAULT_SKIP_PREFIXES.iter().map(|p| p.to_string()).collect()
+        }
+    }
+}
+
+/// Whether the whole message should stay silent.
+pub async fn should_skip(ctx: &Context, guild_id: GuildId, content: &str) -> bool {
+    skips(content, &prefixes(ctx, guild_id).await)
+}
+
+/// Whether `content` is a bot command or starts with one of `prefixes`.
+///
+/// Inline markers are removed first: the default prefix `;` also starts
+/// [`INLINE_MARKER`], and `;;aside;; hello` should still read "hello".
+fn skips(content: &str, prefixes: &[String]) -> bool {
+    let stripped = strip_inline(content);
+    let content = stripped.trim_start();
+    BOT_COMMAND_REGEX.is_match(content) || prefixes.iter().any(|prefix| content.starts_with(prefix.as_str()))
+}
+
+/// Removes the parts of `content` wrapped in inline markers.
+pub fn strip_inline(content: &str) -> String {
+    content
+        .split(INLINE_MARKER)
+        .step_by(2)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<String> {
+        DEFAULT_SKIP_PREFIXES.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn inline_markers_hide_text() {
+        assert_eq!(strip_inline("hello ;;aside;; world").trim(), "hello   world");
+        assert_eq!(strip_inline(";;aside;; hello").trim(), "hello");
+        // An unpaired marker hides the rest of the message
+        assert_eq!(strip_inline("a ;;b;; c ;;d").trim(), "a   c");
+        assert_eq!(strip_inline("no markers"), "no markers");
+    }
+
+    #[test]
+    fn prefixes_and_bot_commands_are_skipped() {
+        let prefixes = defaults();
+        for content in ["!play song", "/cmd", "//comment", "; note", "  ;indented"] {
+            assert!(skips(content, &prefixes), "{:?} should be skipped", content);
+        }
+        for content in [";;aside;; hello", "hello", "! not a command", "a ; b"] {
+            assert!(!skips(content, &prefixes), "{:?} should be read", content);
+        }
+        assert!(skips("#quiet", &["#".to_string()]));
+        assert!(!skips("; read", &["#".to_string()]));
+    }
+}
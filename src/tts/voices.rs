@@ -0,0 +1,28 @@
+//! The voices a guild can choose from.
+//!
+//! The default voice uses `tts.api_url`, and `tts.voices` adds more. A guild
+//! that picked a voice which was later removed from the config falls back to
+//! the default.
+
+use crate::config::{self, VoiceConfig};
+
+/// The ID of the voice at `tts.api_url`.
+pub const DEFAULT: &str = "hiroyuki";
+
+/// Every configured voice, the default one first.
+pub fn all() -> Vec<VoiceConfig> {
+    let tts = &config::get().tts;
+    let default = VoiceConfig {
+        id: DEFAULT.to_string(),
+        name: "Hiroyuki".to_string(),
+        api_url: tts.api_url.clone(),
+    };
+    std::iter::once(default).chain(tts.voices.iter().cloned()).collect()
+}
+
+/// The voice with `id`, or the default one.
+pub fn find(id: Option<&str>) -> VoiceConfig {
+    let mut voices = all();
+    let index = voices.iter().position(|voice| Some(voice.id.as_str()) == id).unwrap_or(0);
+    voices.swap_remove(index)
+}